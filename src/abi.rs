@@ -0,0 +1,251 @@
+use crate::types::{Address, keccak256};
+use serde_json::{Value, json};
+
+// Just enough of the Solidity ABI to put names on calldata, working from the json
+// ABI Blockscout returns for verified contracts.
+
+// The canonical type of an ABI parameter, tuples spelled out as `(type,...)`.
+pub fn canonical_type(param: &Value) -> Option<String> {
+    let typ = param["type"].as_str()?;
+    match typ.strip_prefix("tuple") {
+        Some(suffix) => {
+            let components = param["components"]
+                .as_array()?
+                .iter()
+                .map(canonical_type)
+                .collect::<Option<Vec<String>>>()?;
+            Some(format!("({}){}", components.join(","), suffix))
+        }
+        None => Some(typ.to_string()),
+    }
+}
+
+// `transfer(address,uint256)` for a function or event entry.
+pub fn signature(entry: &Value) -> Option<String> {
+    let name = entry["name"].as_str()?;
+    let inputs = match entry.get("inputs") {
+        Some(inputs) => inputs
+            .as_array()?
+            .iter()
+            .map(canonical_type)
+            .collect::<Option<Vec<String>>>()?,
+        None => Vec::new(),
+    };
+    Some(format!("{}({})", name, inputs.join(",")))
+}
+
+pub fn selector(signature: &str) -> [u8; 4] {
+    let hash = keccak256(signature.as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+// The first four bytes of hex calldata as `0x12345678`, lowercased.
+pub fn calldata_selector(input: &str) -> Option<String> {
+    let hex = input.strip_prefix("0x").unwrap_or(input);
+    let selector = hex.get(..8)?;
+    selector
+        .chars()
+        .all(|c| c.is_ascii_hexdigit())
+        .then(|| format!("0x{}", selector.to_ascii_lowercase()))
+}
+
+// The function of `abi` whose selector is `selector`, as its signature.
+pub fn find_function(abi: &Value, selector: &str) -> Option<String> {
+    abi.as_array()?
+        .iter()
+        .filter(|entry| entry["type"] == "function")
+        .filter_map(signature)
+        .find(|signature| to_hex(&self::selector(signature)) == selector)
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("0x{}", hex)
+}
+
+pub fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+    if !hex.is_ascii() || !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+// A 256 bit big endian word as a decimal string.
+fn word_to_decimal(word: &[u8]) -> String {
+    let mut digits = Vec::new();
+    let mut number = word.to_vec();
+    while number.iter().any(|b| *b != 0) {
+        let mut remainder = 0u32;
+        for byte in number.iter_mut() {
+            let value = (remainder << 8) | *byte as u32;
+            *byte = (value / 10) as u8;
+            remainder = value % 10;
+        }
+        digits.push(b'0' + remainder as u8);
+    }
+    if digits.is_empty() {
+        return "0".into();
+    }
+    digits.reverse();
+    String::from_utf8(digits).unwrap_or_default()
+}
+
+fn word(data: &[u8], at: usize) -> Option<&[u8]> {
+    data.get(at..at.checked_add(32)?)
+}
+
+fn word_to_usize(word: &[u8]) -> Option<usize> {
+    if word[..24].iter().any(|b| *b != 0) {
+        return None;
+    }
+    Some(
+        word[24..]
+            .iter()
+            .fold(0usize, |n, b| (n << 8) | *b as usize),
+    )
+}
+
+pub fn is_dynamic(typ: &str) -> bool {
+    typ == "bytes" || typ == "string" || typ.ends_with("[]")
+}
+
+// A value that fits one word, as Blockscout shows decoded values: numbers as
+// decimal strings, addresses checksummed, fixed bytes as hex.
+pub fn decode_word(typ: &str, word: &[u8]) -> Option<Value> {
+    if typ == "address" {
+        let address: Address = to_hex(&word[12..]).parse().ok()?;
+        return Some(json!(address));
+    }
+    if typ == "bool" {
+        return Some(json!(word[31] != 0));
+    }
+    if typ.starts_with("uint") {
+        return Some(json!(word_to_decimal(word)));
+    }
+    if typ.starts_with("int") {
+        if word[0] & 0x80 == 0 {
+            return Some(json!(word_to_decimal(word)));
+        }
+        // Two's complement, negate to print the magnitude.
+        let mut magnitude: Vec<u8> = word.iter().map(|b| !b).collect();
+        for byte in magnitude.iter_mut().rev() {
+            let (sum, carry) = byte.overflowing_add(1);
+            *byte = sum;
+            if !carry {
+                break;
+            }
+        }
+        return Some(json!(format!("-{}", word_to_decimal(&magnitude))));
+    }
+    if let Some(size) = typ.strip_prefix("bytes") {
+        let size: usize = size.parse().ok().filter(|size| (1..=32).contains(size))?;
+        return Some(json!(to_hex(&word[..size])));
+    }
+    None
+}
+
+// Decodes the value of `typ` whose head is at `at` in `data`. Dynamic values are
+// found through their offset, arrays only of single word elements.
+pub fn decode_value(typ: &str, data: &[u8], at: usize) -> Option<Value> {
+    let head = word(data, at)?;
+    if !is_dynamic(typ) {
+        return decode_word(typ, head);
+    }
+    let offset = word_to_usize(head)?;
+    let len = word_to_usize(word(data, offset)?)?;
+    let start = offset + 32;
+    if let Some(element) = typ.strip_suffix("[]") {
+        if is_dynamic(element) || element.starts_with('(') {
+            return None;
+        }
+        return (0..len)
+            .map(|i| decode_word(element, word(data, start + i * 32)?))
+            .collect::<Option<Vec<Value>>>()
+            .map(Value::Array);
+    }
+    let bytes = data.get(start..start.checked_add(len)?)?;
+    if typ == "string" {
+        return Some(json!(String::from_utf8_lossy(bytes)));
+    }
+    Some(json!(to_hex(bytes)))
+}
+
+#[test]
+fn test_function_signatures() {
+    use serde_json::json;
+
+    let abi = json!([
+        {"type": "function", "name": "transfer", "inputs": [
+            {"name": "to", "type": "address"},
+            {"name": "amount", "type": "uint256"}
+        ]},
+        {"type": "function", "name": "execute", "inputs": [
+            {"name": "calls", "type": "tuple[]", "components": [
+                {"name": "target", "type": "address"},
+                {"name": "data", "type": "bytes"}
+            ]}
+        ]},
+        {"type": "event", "name": "Transfer", "inputs": []}
+    ]);
+    assert_eq!(
+        selector("transfer(address,uint256)"),
+        [0xa9, 0x05, 0x9c, 0xbb]
+    );
+    assert_eq!(
+        signature(&abi[1]).as_deref(),
+        Some("execute((address,bytes)[])")
+    );
+    assert_eq!(
+        calldata_selector("0xA9059CBB000000000000000000000000").as_deref(),
+        Some("0xa9059cbb")
+    );
+    assert_eq!(calldata_selector("0x"), None);
+    assert_eq!(
+        find_function(&abi, "0xa9059cbb").as_deref(),
+        Some("transfer(address,uint256)")
+    );
+    assert_eq!(find_function(&abi, "0xd0e30db0"), None);
+}
+
+#[test]
+fn test_decode_values() {
+    let word = |hex: &str| from_hex(&format!("{:0>64}", hex)).unwrap();
+    assert_eq!(
+        decode_word("uint256", &word("de0b6b3a7640000")),
+        Some(json!("1000000000000000000"))
+    );
+    assert_eq!(
+        decode_word("uint256", &[0xff; 32]),
+        Some(json!(
+            "115792089237316195423570985008687907853269984665640564039457584007913129639935"
+        ))
+    );
+    assert_eq!(decode_word("int24", &[0xff; 32]), Some(json!("-1")));
+    let mut minus_500 = [0xff; 32];
+    minus_500[30..].copy_from_slice(&[0xfe, 0x0c]);
+    assert_eq!(decode_word("int256", &minus_500), Some(json!("-500")));
+    assert_eq!(
+        decode_word("address", &word("d8da6bf26964af9d7eed9e03e53415d37aa96045")),
+        Some(json!("0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045"))
+    );
+    assert_eq!(decode_word("bool", &word("1")), Some(json!(true)));
+    assert_eq!(
+        decode_word("bytes4", &[0xab; 32]),
+        Some(json!("0xabababab"))
+    );
+    assert_eq!(decode_word("tuple", &[0; 32]), None);
+
+    // (uint256[] ids, string uri) with ids = [1, 2] and uri = "ipfs://x".
+    let data = from_hex(&format!(
+        "{:064x}{:064x}{:064x}{:064x}{:064x}{:064x}{:0<64}",
+        64, 160, 2, 1, 2, 8, "697066733a2f2f78"
+    ))
+    .unwrap();
+    assert_eq!(decode_value("uint256[]", &data, 0), Some(json!(["1", "2"])));
+    assert_eq!(decode_value("string", &data, 32), Some(json!("ipfs://x")));
+    assert_eq!(decode_value("string", &data, 224), None);
+}
@@ -0,0 +1,451 @@
+use crate::block_scout_api::{API, GetBlocksParams, GetLogsParams};
+use crate::events;
+use crate::types::Address;
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+// The most logs the explorer returns at once.
+const PAGE_SIZE: usize = 1000;
+// Per event, an owner with more approvals than this is rare.
+const MAX_PAGES: usize = 20;
+const MAX_LABEL_LOOKUPS: usize = 20;
+
+// ERC-20 and ERC-721 share the Approval topic.
+const APPROVAL_TOPIC: &str = "0x8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925";
+const APPROVAL_FOR_ALL_TOPIC: &str =
+    "0x17307eab39ab6107e8899845ad3d59bd9653f200f220920489ca2b5937696c31";
+
+// 2^96 - 1, allowances from here up are "unlimited" approvals. Wallets send 2^256 - 1,
+// tokens with smaller balances cap it, e.g. at 2^96 - 1 or Permit2's 2^160 - 1.
+const UNLIMITED: &str = "79228162514264337593543950335";
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum GrantKind {
+    // An ERC-20 allowance.
+    Allowance,
+    // ERC-721 or ERC-1155 operator approval over every token of the collection.
+    ApprovalForAll,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Grant {
+    pub kind: GrantKind,
+    pub token: Address,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_symbol: Option<String>,
+    pub spender: Address,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spender_name: Option<String>,
+    pub spender_is_contract: Option<bool>,
+    pub spender_is_verified: Option<bool>,
+    // The approved amount in the token's smallest unit, allowances only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowance: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount: Option<f64>,
+    pub unlimited: bool,
+    pub transaction_hash: String,
+    pub block_number: Option<u64>,
+    // Unix seconds of the block.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ApprovalAudit {
+    pub owner: Address,
+    pub logs_scanned: usize,
+    pub pages_fetched: usize,
+    // Older logs were left, grants made before the ones read may be missing.
+    pub truncated: bool,
+    // Outstanding grants, unlimited ones first.
+    pub approvals: Vec<Grant>,
+    // By event, the grants of the other one are still reported.
+    pub errors: BTreeMap<String, String>,
+}
+
+// Token, spender and kind, a later grant for the same key replaces the earlier one.
+type GrantKey = (Address, Address, GrantKind);
+
+// Compares unsigned decimal strings without leading zeros.
+fn at_least(value: &str, threshold: &str) -> bool {
+    (value.len(), value) >= (threshold.len(), threshold)
+}
+
+fn is_unlimited(value: &str) -> bool {
+    at_least(value, UNLIMITED)
+}
+
+// The Etherscan compatible api encodes numbers as hex strings.
+fn hex_number(value: &Value) -> Option<u64> {
+    u64::from_str_radix(value.as_str()?.strip_prefix("0x")?, 16).ok()
+}
+
+fn parameter(decoded: &Value, index: usize) -> &Value {
+    &decoded["parameters"][index]["value"]
+}
+
+// A grant made by `owner` in this log and whether it is still active, an allowance
+// of zero or an operator approval set to false revoke the earlier grant.
+fn grant(owner: &Address, log: &Value) -> Option<(Grant, bool)> {
+    let decoded = events::decode_log(log)?;
+    let method_call = decoded["method_call"].as_str()?;
+    let kind = if method_call.starts_with("ApprovalForAll(") {
+        GrantKind::ApprovalForAll
+    } else if method_call.starts_with("Approval(") && decoded["parameters"][2]["indexed"] == false {
+        GrantKind::Allowance
+    } else {
+        // ERC-721 single token approvals end with the next transfer, skip them.
+        return None;
+    };
+    let approver: Address = parameter(&decoded, 0).as_str()?.parse().ok()?;
+    if approver != *owner {
+        return None;
+    }
+    let (allowance, active) = match (kind, parameter(&decoded, 2)) {
+        (GrantKind::Allowance, Value::String(value)) => (Some(value.clone()), value != "0"),
+        (GrantKind::ApprovalForAll, Value::Bool(approved)) => (None, *approved),
+        (GrantKind::ApprovalForAll, Value::String(approved)) => (None, approved == "true"),
+        _ => return None,
+    };
+    let grant = Grant {
+        kind,
+        token: log["address"].as_str()?.parse().ok()?,
+        token_name: None,
+        token_symbol: None,
+        spender: parameter(&decoded, 1).as_str()?.parse().ok()?,
+        spender_name: None,
+        spender_is_contract: None,
+        spender_is_verified: None,
+        unlimited: kind == GrantKind::ApprovalForAll
+            || allowance.as_deref().is_some_and(is_unlimited),
+        allowance,
+        amount: None,
+        transaction_hash: log["transactionHash"].as_str()?.into(),
+        block_number: hex_number(&log["blockNumber"]),
+        timestamp: hex_number(&log["timeStamp"]),
+    };
+    Some((grant, active))
+}
+
+async fn get_logs(
+    api: &API,
+    chain_id: i32,
+    owner: &Address,
+    topic: &str,
+    blocks: (u64, Option<u64>),
+    page_size: usize,
+) -> Result<Vec<Value>> {
+    let params = GetLogsParams {
+        from_block: blocks.0.to_string(),
+        to_block: blocks
+            .1
+            .map_or_else(|| "latest".into(), |block| block.to_string()),
+        topic0: topic.into(),
+        topic1: format!("0x{:0>64}", owner.as_str()[2..].to_ascii_lowercase()),
+        topic0_1_opr: "and".into(),
+        page: 1,
+        offset: page_size,
+    };
+    let result = api.get_logs(chain_id, params).await?;
+    Ok(result.as_array().cloned().unwrap_or_default())
+}
+
+fn log_block(log: &Value) -> u64 {
+    hex_number(&log["blockNumber"]).unwrap_or_default()
+}
+
+// Every log of `topic` with `owner` as its first indexed argument, the pages fetched
+// and whether older logs were left. The explorer returns the oldest logs first, but
+// the latest ones decide which grants are live, so an owner with more than a page
+// is read backwards from the latest block in windows, halving a window that fills
+// a page and doubling one that comes back sparse. Out of pages, the newest logs are
+// the ones kept.
+async fn owner_logs(
+    api: &API,
+    chain_id: i32,
+    owner: &Address,
+    topic: &str,
+    page_size: usize,
+    max_pages: usize,
+) -> Result<(Vec<Value>, usize, bool)> {
+    let mut oldest = get_logs(api, chain_id, owner, topic, (0, None), page_size).await?;
+    if oldest.len() < page_size {
+        return Ok((oldest, 1, false));
+    }
+    // Its last block may go on in the next page, it is read again from the back.
+    let floor = oldest.last().map(log_block).unwrap_or_default();
+    oldest.retain(|log| log_block(log) < floor);
+    let blocks = api.get_blocks(chain_id, GetBlocksParams::default()).await?;
+    let mut to = blocks["items"][0]["height"]
+        .as_u64()
+        .ok_or_else(|| anyhow::anyhow!("latest block unknown"))?
+        .max(floor);
+    let mut span = to - floor + 1;
+    let mut logs = Vec::new();
+    let mut pages = 1;
+    let mut lost = false;
+    loop {
+        if pages == max_pages {
+            return Ok((logs, pages, true));
+        }
+        let from = to.saturating_sub(span - 1).max(floor);
+        let window = get_logs(api, chain_id, owner, topic, (from, Some(to)), page_size).await?;
+        pages += 1;
+        if window.len() == page_size && from < to {
+            span = (to - from).div_ceil(2);
+            continue;
+        }
+        // A single block with a page of approvals, the rest of it is lost.
+        lost |= window.len() == page_size;
+        if window.len() < page_size / 2 {
+            span = span.saturating_mul(2);
+        }
+        logs.extend(window);
+        if from == floor {
+            logs.extend(oldest);
+            return Ok((logs, pages, lost));
+        }
+        to = from - 1;
+    }
+}
+
+// Outstanding approvals of `owner`, rebuilt from every Approval and ApprovalForAll log
+// naming it as the owner. Whoever sent the transaction, so approvals through routers,
+// multicalls and `permit` count, and so do contract wallets. Allowances are the last
+// approved amounts, tokens that emit no Approval when transferFrom spends them look
+// untouched. Permit2 keeps allowances of its own, only the approval of Permit2 shows.
+pub async fn audit(api: &API, chain_id: i32, owner: Address) -> Result<ApprovalAudit> {
+    scan(api, chain_id, owner, PAGE_SIZE, MAX_PAGES).await
+}
+
+async fn scan(
+    api: &API,
+    chain_id: i32,
+    owner: Address,
+    page_size: usize,
+    max_pages: usize,
+) -> Result<ApprovalAudit> {
+    let events = [
+        ("Approval", APPROVAL_TOPIC),
+        ("ApprovalForAll", APPROVAL_FOR_ALL_TOPIC),
+    ];
+    let results = futures::future::join_all(events.map(|(event, topic)| {
+        let owner = &owner;
+        async move {
+            let logs = owner_logs(api, chain_id, owner, topic, page_size, max_pages).await;
+            (event, logs)
+        }
+    }))
+    .await;
+
+    let mut errors = BTreeMap::new();
+    let mut logs_scanned = 0;
+    let mut pages_fetched = 0;
+    let mut truncated = false;
+    // The latest grant per token and spender wins, revocations included.
+    let mut latest: HashMap<GrantKey, ((u64, u64), bool, Grant)> = HashMap::new();
+    for (event, logs) in results {
+        let (logs, pages, more) = match logs {
+            Ok(logs) => logs,
+            Err(e) => {
+                errors.insert(event.to_string(), e.to_string());
+                continue;
+            }
+        };
+        logs_scanned += logs.len();
+        pages_fetched += pages;
+        truncated |= more;
+        for log in &logs {
+            let Some((grant, active)) = grant(&owner, log) else {
+                continue;
+            };
+            let order = (
+                grant.block_number.unwrap_or_default(),
+                hex_number(&log["logIndex"]).unwrap_or_default(),
+            );
+            let key = (grant.token.clone(), grant.spender.clone(), grant.kind);
+            if latest
+                .get(&key)
+                .is_none_or(|(latest, _, _)| *latest < order)
+            {
+                latest.insert(key, (order, active, grant));
+            }
+        }
+    }
+    // Nothing to report is not the same as no approvals.
+    if errors.len() == events.len() {
+        anyhow::bail!("failed to fetch approval logs: {}", errors["Approval"]);
+    }
+    let mut grants: Vec<Grant> = latest
+        .into_values()
+        .filter(|(_, active, _)| *active)
+        .map(|(_, _, grant)| grant)
+        .collect();
+    label(api, chain_id, &mut grants).await;
+    grants.sort_by(|a, b| {
+        b.unlimited
+            .cmp(&a.unlimited)
+            .then(b.block_number.cmp(&a.block_number))
+    });
+    Ok(ApprovalAudit {
+        owner,
+        logs_scanned,
+        pages_fetched,
+        truncated,
+        approvals: grants,
+        errors,
+    })
+}
+
+// Best effort names and verification for spenders, symbols and decimals for tokens.
+async fn label(api: &API, chain_id: i32, grants: &mut [Grant]) {
+    let mut spenders = Vec::<Address>::new();
+    let mut tokens = Vec::<Address>::new();
+    for grant in grants.iter() {
+        if !spenders.contains(&grant.spender) && spenders.len() < MAX_LABEL_LOOKUPS {
+            spenders.push(grant.spender.clone());
+        }
+        if !tokens.contains(&grant.token) && tokens.len() < MAX_LABEL_LOOKUPS {
+            tokens.push(grant.token.clone());
+        }
+    }
+    let spenders = futures::future::join_all(spenders.into_iter().map(|spender| async move {
+        let info = api.get_address_info(chain_id, spender.clone()).await;
+        (spender, info.ok())
+    }));
+    let tokens = futures::future::join_all(tokens.into_iter().map(|token| async move {
+        let info = api.get_token_info(chain_id, token.clone()).await;
+        (token, info.ok())
+    }));
+    let (spenders, tokens) = tokio::join!(spenders, tokens);
+    let spenders: HashMap<Address, Value> = spenders
+        .into_iter()
+        .filter_map(|(address, info)| Some((address, info?)))
+        .collect();
+    let tokens: HashMap<Address, Value> = tokens
+        .into_iter()
+        .filter_map(|(address, info)| Some((address, info?)))
+        .collect();
+    for grant in grants {
+        if let Some(info) = spenders.get(&grant.spender) {
+            grant.spender_name = info["ens_domain_name"]
+                .as_str()
+                .or_else(|| info["name"].as_str())
+                .map(String::from);
+            grant.spender_is_contract = info["is_contract"].as_bool();
+            grant.spender_is_verified = info["is_verified"].as_bool();
+        }
+        if let Some(info) = tokens.get(&grant.token) {
+            grant.token_name = info["name"].as_str().map(String::from);
+            grant.token_symbol = info["symbol"].as_str().map(String::from);
+            // More than there will ever be to spend.
+            if let (Some(allowance), Some(total_supply)) = (
+                &grant.allowance,
+                info["total_supply"]
+                    .as_str()
+                    .filter(|supply| *supply != "0"),
+            ) {
+                grant.unlimited |= at_least(allowance, total_supply);
+            }
+            let decimals = info["decimals"]
+                .as_str()
+                .and_then(|d| d.parse::<i32>().ok());
+            if let (Some(allowance), Some(decimals), false) =
+                (&grant.allowance, decimals, grant.unlimited)
+            {
+                grant.amount = allowance
+                    .parse::<f64>()
+                    .ok()
+                    .map(|allowance| allowance / 10f64.powi(decimals));
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_audit() {
+    let mock = crate::mock_blockscout::MockBlockscout::start()
+        .await
+        .unwrap();
+    let api = mock.api();
+    let owner: Address = "0x1111111111111111111111111111111111111111"
+        .parse()
+        .unwrap();
+    let spenders = |audit: &ApprovalAudit| -> Vec<(GrantKind, String)> {
+        audit
+            .approvals
+            .iter()
+            .map(|grant| (grant.kind, grant.spender.to_string()[2..3].to_string()))
+            .collect()
+    };
+
+    let audit = audit(&api, 1, owner.clone()).await.unwrap();
+    assert_eq!(audit.logs_scanned, 6);
+    assert_eq!(audit.pages_fetched, 2);
+    assert!(!audit.truncated && audit.errors.is_empty());
+    // The USDC allowance of 0x8888 was revoked, its DAI one is still there. 0x6666 got
+    // its DAI allowance with a permit, in a transaction the owner never sent.
+    assert_eq!(
+        spenders(&audit),
+        vec![
+            (GrantKind::ApprovalForAll, "9".to_string()),
+            (GrantKind::Allowance, "7".to_string()),
+            (GrantKind::Allowance, "6".to_string()),
+            (GrantKind::Allowance, "8".to_string()),
+        ]
+    );
+    let router = &audit.approvals[1];
+    assert!(router.unlimited);
+    assert_eq!(router.token_symbol.as_deref(), Some("USDC"));
+    assert_eq!(router.spender_name.as_deref(), Some("Uniswap V2: Router 2"));
+    assert_eq!(router.spender_is_verified, Some(true));
+    assert_eq!(router.timestamp, Some(1704074400));
+    let permit = &audit.approvals[2];
+    assert_eq!(permit.amount, Some(100.0));
+    assert_eq!(
+        permit.transaction_hash,
+        "0x0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c"
+    );
+    let dai = &audit.approvals[3];
+    assert_eq!(dai.token_symbol.as_deref(), Some("DAI"));
+    assert_eq!(dai.amount, Some(250.0));
+    assert_eq!(audit.approvals[0].token_symbol.as_deref(), Some("BAYC"));
+    assert_eq!(audit.approvals[0].spender_is_verified, Some(false));
+
+    assert!(is_unlimited("79228162514264337593543950335"));
+    assert!(is_unlimited(
+        "1461501637330902918203684832716283019655932542975"
+    ));
+    assert!(at_least("1000", "999") && !at_least("999", "1000"));
+
+    // With pages of two logs the first one is full, the rest is read back from the
+    // latest block, 120 on this chain, in windows of at most two logs.
+    let audit = scan(&api, 8453, owner.clone(), 2, 20).await.unwrap();
+    assert!(!audit.truncated);
+    assert_eq!(audit.logs_scanned, 6);
+    assert_eq!(audit.pages_fetched, 12);
+    assert_eq!(spenders(&audit).len(), 4);
+    assert!(mock.requests().contains(&format!(
+        "8453:api?module=logs&action=getLogs&fromBlock=107&toBlock=108&topic0={}&topic1=0x{:0>64}&topic0_1_opr=and&page=1&offset=2",
+        APPROVAL_TOPIC,
+        "1".repeat(40)
+    )));
+
+    // Out of pages, the newest grant survives and the older ones are left out.
+    let audit = scan(&api, 8453, owner, 2, 7).await.unwrap();
+    assert!(audit.truncated);
+    assert_eq!(audit.logs_scanned, 2);
+    assert_eq!(
+        spenders(&audit),
+        vec![
+            (GrantKind::ApprovalForAll, "9".to_string()),
+            (GrantKind::Allowance, "6".to_string()),
+        ]
+    );
+}
@@ -0,0 +1,142 @@
+use anyhow::{Result, anyhow};
+use axum::{
+    extract::{Request, State},
+    http::{HeaderMap, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+
+pub const API_KEYS_ENV: &str = "MCP_API_KEYS";
+pub const API_KEYS_FILE_ENV: &str = "MCP_API_KEYS_FILE";
+pub const ADMIN_API_KEYS_ENV: &str = "MCP_ADMIN_API_KEYS";
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+#[derive(Clone, Default)]
+pub struct ApiKeys {
+    keys: Arc<Vec<String>>,
+}
+
+impl ApiKeys {
+    pub fn new(keys: Vec<String>) -> Self {
+        let keys = keys
+            .into_iter()
+            .map(|key| key.trim().to_string())
+            .filter(|key| !key.is_empty() && !key.starts_with('#'))
+            .collect();
+        ApiKeys {
+            keys: Arc::new(keys),
+        }
+    }
+
+    // Keys come from a comma separated env var and/or a file with one key per line.
+    pub fn from_env() -> Result<Self> {
+        let mut keys = Vec::<String>::new();
+        if let Ok(value) = std::env::var(API_KEYS_ENV) {
+            keys.extend(value.split(',').map(String::from));
+        }
+        if let Ok(path) = std::env::var(API_KEYS_FILE_ENV) {
+            let content = std::fs::read_to_string(&path)
+                .map_err(|e| anyhow!("failed to read api keys file {}: {}", path, e))?;
+            keys.extend(content.lines().map(String::from));
+        }
+        Ok(Self::new(keys))
+    }
+
+    // Keys for the admin endpoints, kept apart from the /mcp ones.
+    pub fn admin_from_env() -> Self {
+        match std::env::var(ADMIN_API_KEYS_ENV) {
+            Ok(value) => Self::new(value.split(',').map(String::from).collect()),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn verify(&self, key: &str) -> bool {
+        // Check every key so the time taken does not reveal which one matched.
+        self.keys.iter().fold(false, |found, k| {
+            constant_time_eq(k.as_bytes(), key.as_bytes()) | found
+        })
+    }
+
+    pub fn authorize(&self, headers: &HeaderMap) -> bool {
+        match extract_key(headers) {
+            Some(key) => self.verify(key),
+            None => false,
+        }
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+pub fn extract_bearer(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return None;
+    }
+    Some(token.trim())
+}
+
+fn extract_key(headers: &HeaderMap) -> Option<&str> {
+    extract_bearer(headers).or_else(|| {
+        headers
+            .get(API_KEY_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+    })
+}
+
+pub fn unauthorized(www_authenticate: String, message: &str) -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        [(header::WWW_AUTHENTICATE, www_authenticate)],
+        message.to_string(),
+    )
+        .into_response()
+}
+
+pub async fn require_api_key(
+    State(keys): State<ApiKeys>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !keys.authorize(request.headers()) {
+        return unauthorized(
+            "Bearer error=\"invalid_token\"".into(),
+            "missing or invalid api key",
+        );
+    }
+    next.run(request).await
+}
+
+#[test]
+fn test_authorize() {
+    let keys = ApiKeys::new(vec!["secret".into(), " # comment".into(), "".into()]);
+    assert_eq!(keys.len(), 1);
+
+    let mut headers = HeaderMap::new();
+    assert!(!keys.authorize(&headers));
+
+    headers.insert(header::AUTHORIZATION, "Bearer secret".parse().unwrap());
+    assert!(keys.authorize(&headers));
+
+    headers.insert(header::AUTHORIZATION, "Bearer secrets".parse().unwrap());
+    assert!(!keys.authorize(&headers));
+
+    headers.remove(header::AUTHORIZATION);
+    headers.insert(API_KEY_HEADER, "secret".parse().unwrap());
+    assert!(keys.authorize(&headers));
+}
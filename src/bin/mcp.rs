@@ -1,7 +1,20 @@
-use blocks_mcp::tools::OnChainData;
-use rmcp::transport::streamable_http_server::{
-    StreamableHttpService, session::local::LocalSessionManager,
+use axum::{extract::State, http::header, response::IntoResponse};
+use blocks_mcp::{
+    auth::{self, ApiKeys},
+    block_scout_api::API,
+    health,
+    metrics::METRICS,
+    oauth::{self, OAuthConfig, ProtectedResource},
+    origin::{self, OriginPolicy},
+    sessions::{self, SessionSettings, TrackedSessionManager},
+    shutdown::{self, Drain},
+    telemetry,
+    tools::OnChainData,
 };
+use rmcp::transport::streamable_http_server::{StreamableHttpService, session::SessionManager};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
 use tracing_subscriber::{
     layer::SubscriberExt,
     util::SubscriberInitExt,
@@ -9,27 +22,197 @@ use tracing_subscriber::{
 };
 
 const BIND_ADDRESS: &str = "0.0.0.0:8000";
+const LOG_FORMAT_ENV: &str = "LOG_FORMAT";
+const SERVER_STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+async fn metrics(State(session_manager): State<Arc<TrackedSessionManager>>) -> impl IntoResponse {
+    METRICS.set_active_sessions(session_manager.len());
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        METRICS.encode(),
+    )
+}
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn main() -> anyhow::Result<ExitCode> {
+    let json_logs = std::env::var(LOG_FORMAT_ENV).is_ok_and(|format| format == "json");
+    let (otel_layer, tracer_provider) = match telemetry::layer_from_env()? {
+        Some((layer, provider)) => (Some(layer), Some(provider)),
+        None => (None, None),
+    };
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| "debug".to_string().into()),
         )
-        .with(tracing_subscriber::fmt::layer())
+        .with(json_logs.then(|| {
+            tracing_subscriber::fmt::layer()
+                .json()
+                .with_current_span(true)
+                .with_span_list(true)
+        }))
+        .with((!json_logs).then(tracing_subscriber::fmt::layer))
+        .with(otel_layer)
         .init();
 
+    let api = API::from_env()?;
+    let drain = Drain::default();
+    let session_settings = SessionSettings::from_env()?;
+    let server_config = session_settings.server_config();
+    let session_manager = Arc::new(TrackedSessionManager::new(session_settings));
+    tokio::spawn(session_manager.clone().run_reaper());
     let service = StreamableHttpService::new(
-        || Ok(OnChainData::new()),
-        LocalSessionManager::default().into(),
-        Default::default(),
+        {
+            let api = api.clone();
+            let drain = drain.clone();
+            move || Ok(OnChainData::with_api(api.clone()).with_drain(drain.clone()))
+        },
+        session_manager.clone(),
+        server_config,
     );
 
-    let router = axum::Router::new().nest_service("/mcp", service);
+    let mut mcp_router = axum::Router::new()
+        .nest_service("/mcp", service)
+        .route_layer(axum::middleware::from_fn_with_state(
+            session_manager.clone(),
+            sessions::limit_sessions,
+        ))
+        .route_layer(axum::middleware::from_fn_with_state(
+            drain.clone(),
+            shutdown::reject_new_sessions,
+        ));
+    let health_router = axum::Router::new()
+        .route("/healthz", axum::routing::get(health::healthz))
+        .route("/readyz", axum::routing::get(health::readyz))
+        .with_state(health::ReadinessCheck::new(api));
+    let mut router = axum::Router::new()
+        .route("/metrics", axum::routing::get(metrics))
+        .with_state(session_manager.clone())
+        .merge(health_router);
+    let admin_api_keys = ApiKeys::admin_from_env();
+    let admin_router = if admin_api_keys.is_empty() {
+        tracing::info!(
+            "admin endpoints are disabled, set {} to enable them",
+            auth::ADMIN_API_KEYS_ENV
+        );
+        axum::Router::new()
+    } else {
+        axum::Router::new()
+            .route(
+                "/admin/sessions",
+                axum::routing::get(sessions::list_sessions),
+            )
+            .with_state(session_manager.clone())
+            .route_layer(axum::middleware::from_fn_with_state(
+                admin_api_keys,
+                auth::require_api_key,
+            ))
+    };
+    let api_keys = ApiKeys::from_env()?;
+    if let Some(oauth_config) = OAuthConfig::from_env()? {
+        tracing::info!(
+            "/mcp requires access tokens issued by {}",
+            oauth_config.issuer
+        );
+        let protected_resource = ProtectedResource::new(oauth_config, api_keys).await?;
+        let metadata_router = axum::Router::new()
+            .route(
+                oauth::METADATA_PATH,
+                axum::routing::get(oauth::protected_resource_metadata),
+            )
+            .route(
+                &format!("{}/mcp", oauth::METADATA_PATH),
+                axum::routing::get(oauth::protected_resource_metadata),
+            )
+            .with_state(protected_resource.clone());
+        router = router.merge(metadata_router);
+        mcp_router = mcp_router.route_layer(axum::middleware::from_fn_with_state(
+            protected_resource,
+            oauth::require_access_token,
+        ));
+    } else if api_keys.is_empty() {
+        tracing::warn!(
+            "no api keys configured, /mcp is open to everyone, set {} or {}",
+            auth::API_KEYS_ENV,
+            auth::API_KEYS_FILE_ENV
+        );
+    } else {
+        tracing::info!("/mcp requires one of {} api keys", api_keys.len());
+        mcp_router = mcp_router.route_layer(axum::middleware::from_fn_with_state(
+            api_keys,
+            auth::require_api_key,
+        ));
+    }
+
+    let origin_policy = OriginPolicy::from_env();
+    if origin_policy.restricts_hosts() {
+        tracing::info!(
+            "/mcp accepts local Host headers only, add the names this server is reached by to {}",
+            origin::ALLOWED_HOSTS_ENV
+        );
+    } else {
+        tracing::warn!(
+            "/mcp accepts any Host header, {} allows *",
+            origin::ALLOWED_HOSTS_ENV
+        );
+    }
+    // Probes and scrapers reach the server by pod ip, only the browser facing routes
+    // check the Host and Origin.
+    let mcp_router = mcp_router
+        .merge(admin_router)
+        .layer(axum::middleware::from_fn_with_state(
+            origin_policy.clone(),
+            origin::validate_origin,
+        ));
+    let router = router.merge(mcp_router).layer(origin_policy.cors_layer());
     let tcp_listener = tokio::net::TcpListener::bind(BIND_ADDRESS).await?;
-    let _ = axum::serve(tcp_listener, router)
-        .with_graceful_shutdown(async { tokio::signal::ctrl_c().await.unwrap() })
-        .await;
-    Ok(())
+    let server = tokio::spawn(
+        axum::serve(tcp_listener, router)
+            .with_graceful_shutdown(drain.clone().started())
+            .into_future(),
+    );
+
+    shutdown::signal().await;
+    let grace_period = Drain::grace_period_from_env();
+    tracing::info!(
+        "shutting down, waiting up to {:?} for {} in-flight tool calls",
+        grace_period,
+        drain.in_flight()
+    );
+    drain.start();
+    let drained = drain.wait_idle(grace_period).await;
+    if !drained {
+        tracing::warn!(
+            "grace period elapsed, cancelling {} in-flight tool calls",
+            drain.in_flight()
+        );
+        drain.cancel();
+    }
+
+    // Open SSE streams would otherwise keep their connections, and the server, alive.
+    for session_id in session_manager.session_ids() {
+        if let Err(e) = session_manager.close_session(&session_id).await {
+            tracing::warn!(
+                "failed to close session {}: {}",
+                sessions::hash_session_id(&session_id),
+                e
+            );
+        }
+    }
+    match tokio::time::timeout(SERVER_STOP_TIMEOUT, server).await {
+        Ok(Ok(Ok(()))) => {}
+        Ok(Ok(Err(e))) => tracing::error!("server error: {}", e),
+        Ok(Err(e)) => tracing::error!("server task failed: {}", e),
+        Err(_) => tracing::warn!("connections still open after {:?}", SERVER_STOP_TIMEOUT),
+    }
+
+    if let Some(tracer_provider) = tracer_provider {
+        tracer_provider.shutdown()?;
+    }
+    if drained {
+        tracing::info!("shutdown complete");
+        Ok(ExitCode::SUCCESS)
+    } else {
+        Ok(ExitCode::FAILURE)
+    }
 }
@@ -1,21 +1,87 @@
+use crate::cassette::{Cassette, CassetteMode, Recording};
+use crate::ens::{ENS_CHAIN_ID, ENS_RPC_URL_ENV, EnsRpc};
+use crate::metrics::METRICS;
+use crate::telemetry::inject_trace_context;
+use crate::types::{Address, BlockId, TxHash};
 use anyhow::{Result, anyhow};
 use hyper::StatusCode;
+use hyper::header::{HeaderMap, HeaderValue};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
+use tracing::Instrument;
+
+const CHAINS_REGISTRY_URL: &str = "https://chains.blockscout.com/api/chains";
+
+pub const CHAINS_CONFIG_ENV: &str = "BLOCKSCOUT_CHAINS_CONFIG";
+pub const CHAINS_REGISTRY_ENV: &str = "BLOCKSCOUT_CHAINS_REGISTRY_URL";
+pub const NAME_SERVICE_ENV: &str = "BLOCKSCOUT_NAME_SERVICE_URL";
+
+const NAME_SERVICE_URL: &str = "https://bens.services.blockscout.com";
+
+pub const MERLIN_CHAIN_ID: i32 = 4200;
+const MERLIN_EXPLORER_URL: &str = "https://scan.merlinverify.com/";
+
+// Primary names by chain and address, None when the address has no name. A failed
+// lookup is kept with the time it failed, so a broken name service is not asked again
+// for every address of every call.
+type NameCache = HashMap<(i32, Address), Result<Option<String>, Instant>>;
+
+const NAME_FAILURE_TTL: Duration = Duration::from_secs(60);
 
 #[derive(Clone)]
 pub struct API {
     pub cached_chains: Arc<RwLock<HashMap<i32, Chain>>>,
+    all_chains_cached: Arc<AtomicBool>,
+    explorers: Arc<HashMap<i32, ExplorerConfig>>,
+    configured_chains: Arc<[i32]>,
+    chains_registry_url: Arc<str>,
+    cassette: Option<Arc<Cassette>>,
+    name_service_url: Arc<str>,
+    ens_rpc: Option<EnsRpc>,
+    cached_names: Arc<RwLock<NameCache>>,
+}
+
+// Per chain explorer settings, they take precedence over the chain registry.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct ExplorerConfig {
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+impl fmt::Debug for ExplorerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExplorerConfig")
+            .field("url", &self.url)
+            .field("api_key", &self.api_key.as_ref().map(|_| "<redacted>"))
+            .field(
+                "headers",
+                &self
+                    .headers
+                    .keys()
+                    .map(|k| (k, "<redacted>"))
+                    .collect::<HashMap<_, _>>(),
+            )
+            .finish()
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Chain {
     name: String,
+    #[serde(default)]
     description: String,
     #[serde(rename = "isTestnet")]
+    #[serde(default)]
     is_test_net: bool,
     explorers: Vec<ChainExplorer>,
 }
@@ -25,7 +91,16 @@ pub struct ChainExplorer {
     url: String,
 }
 
+#[allow(clippy::needless_arbitrary_self_type, clippy::len_zero)]
 impl Chain {
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_testnet(&self) -> bool {
+        self.is_test_net
+    }
+
     pub fn get_url(self: &Self) -> Result<String> {
         if self.explorers.len() > 0 {
             return Ok(self.explorers[0].url.clone());
@@ -34,6 +109,28 @@ impl Chain {
     }
 }
 
+// An explorer answered with something other than 200, callers can downcast to tell
+// a missing address or transaction apart from an outage.
+#[derive(Debug)]
+pub struct StatusError(pub StatusCode);
+
+impl fmt::Display for StatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "request failed: {}", self.0)
+    }
+}
+
+impl std::error::Error for StatusError {}
+
+impl StatusError {
+    pub fn is_not_found(e: &anyhow::Error) -> bool {
+        matches!(
+            e.downcast_ref::<StatusError>(),
+            Some(StatusError(StatusCode::NOT_FOUND))
+        )
+    }
+}
+
 #[derive(Serialize, Deserialize, Default)]
 pub struct SearchParams {
     pub q: String,
@@ -92,6 +189,9 @@ pub struct GetAddressTokensParams {
     #[serde(skip_serializing_if = "String::is_empty")]
     #[serde(rename = "type")]
     pub typ: String,
+    // The `next_page_params` of the previous page, empty for the first one.
+    #[serde(flatten)]
+    pub page: BTreeMap<String, String>,
 }
 
 #[derive(Serialize, Deserialize, Default)]
@@ -110,47 +210,344 @@ pub struct GetTokensParams {
     pub typ: String,
 }
 
+// The Etherscan compatible `getLogs`, `/api/v2` can't filter logs by topic.
+#[derive(Serialize, Deserialize, Default)]
+pub struct GetLogsParams {
+    #[serde(rename = "fromBlock")]
+    pub from_block: String,
+    #[serde(rename = "toBlock")]
+    pub to_block: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub topic0: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub topic1: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub topic0_1_opr: String,
+    // 1 based, of `offset` logs each.
+    pub page: usize,
+    pub offset: usize,
+}
+
+impl Default for API {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// The query of the page after `page`, None on the last page. Null parameters are left
+// out, the explorer reads a missing one as null.
+pub fn next_page(page: &Value) -> Option<BTreeMap<String, String>> {
+    let params = page["next_page_params"].as_object()?;
+    Some(
+        params
+            .iter()
+            .filter_map(|(name, value)| match value {
+                Value::Null => None,
+                Value::String(value) => Some((name.clone(), value.clone())),
+                value => Some((name.clone(), value.to_string())),
+            })
+            .collect(),
+    )
+}
+
+#[allow(clippy::needless_arbitrary_self_type, clippy::unnecessary_unwrap)]
 impl API {
     pub fn new() -> Self {
+        Self::with_explorers(HashMap::new())
+    }
+
+    pub fn with_explorers(mut explorers: HashMap<i32, ExplorerConfig>) -> Self {
+        let mut configured_chains: Vec<i32> = explorers.keys().copied().collect();
+        configured_chains.sort();
+        let merlin = explorers.entry(MERLIN_CHAIN_ID).or_default();
+        if merlin.url.is_none() {
+            merlin.url = Some(MERLIN_EXPLORER_URL.into());
+        }
+        for explorer in explorers.values_mut() {
+            if let Some(url) = explorer.url.as_mut()
+                && !url.ends_with('/')
+            {
+                url.push('/');
+            }
+        }
         API {
             cached_chains: Arc::new(RwLock::new(HashMap::<i32, Chain>::new())),
+            all_chains_cached: Arc::new(AtomicBool::new(false)),
+            explorers: Arc::new(explorers),
+            configured_chains: configured_chains.into(),
+            chains_registry_url: CHAINS_REGISTRY_URL.into(),
+            cassette: None,
+            name_service_url: NAME_SERVICE_URL.into(),
+            ens_rpc: None,
+            cached_names: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    // Points the chain registry somewhere else, e.g. a mirror or a local fake in tests.
+    pub fn with_chains_registry(mut self, url: &str) -> Self {
+        self.chains_registry_url = url.trim_end_matches('/').into();
+        self
+    }
+
+    pub fn with_cassette(mut self, cassette: Cassette) -> Self {
+        self.cassette = Some(Arc::new(cassette));
+        self
+    }
+
+    pub fn with_name_service(mut self, url: &str) -> Self {
+        self.name_service_url = url.trim_end_matches('/').into();
+        self
+    }
+
+    // Resolves mainnet names on chain through this rpc instead of the Blockscout name
+    // service, other chains keep using the name service.
+    pub fn with_ens_rpc(mut self, ens_rpc: EnsRpc) -> Self {
+        self.ens_rpc = Some(ens_rpc);
+        self
+    }
+
+    // The config file is a json object keyed by chain id, e.g.
+    // {"1": {"api_key": "..."}, "100": {"url": "https://...", "headers": {"x-token": "..."}}}
+    pub fn from_env() -> Result<Self> {
+        let api = match std::env::var(CHAINS_CONFIG_ENV) {
+            Ok(path) => {
+                let content = std::fs::read_to_string(&path)
+                    .map_err(|e| anyhow!("failed to read chains config {}: {}", path, e))?;
+                let explorers: HashMap<i32, ExplorerConfig> = serde_json::from_str(&content)
+                    .map_err(|e| anyhow!("invalid chains config {}: {}", path, e))?;
+                Self::with_explorers(explorers)
+            }
+            Err(_) => Self::new(),
+        };
+        let api = match std::env::var(CHAINS_REGISTRY_ENV) {
+            Ok(url) if !url.is_empty() => api.with_chains_registry(&url),
+            _ => api,
+        };
+        let api = match std::env::var(NAME_SERVICE_ENV) {
+            Ok(url) if !url.is_empty() => api.with_name_service(&url),
+            _ => api,
+        };
+        let api = match std::env::var(ENS_RPC_URL_ENV) {
+            Ok(url) if !url.is_empty() => api.with_ens_rpc(EnsRpc::new(&url)),
+            _ => api,
+        };
+        match Cassette::from_env()? {
+            Some(cassette) => Ok(api.with_cassette(cassette)),
+            None => Ok(api),
         }
     }
 
+    // The chains the operator set up an explorer for.
+    pub fn configured_chain_ids(&self) -> Vec<i32> {
+        self.configured_chains.to_vec()
+    }
+
+    // Every chain with an explorer known up front, built-in ones like Merlin included.
+    pub fn explorer_chain_ids(&self) -> Vec<i32> {
+        let mut chain_ids: Vec<i32> = self.explorers.keys().copied().collect();
+        chain_ids.sort();
+        chain_ids
+    }
+
+    pub async fn get_chains(&self) -> Result<HashMap<i32, Chain>> {
+        if self.all_chains_cached.load(Ordering::Acquire) {
+            return Ok(self.cached_chains.read().await.clone());
+        }
+
+        let (status, body) = self
+            .fetch(
+                "registry",
+                "chains",
+                reqwest::Client::new().get(&*self.chains_registry_url),
+            )
+            .await?;
+
+        if status != StatusCode::OK {
+            return Err(anyhow!("request failed: {}", status));
+        }
+
+        let chains: HashMap<String, Chain> = serde_json::from_slice(&body)?;
+        let mut write_lock = self.cached_chains.write().await;
+        for (chain_id, chain) in chains {
+            if let Ok(chain_id) = chain_id.parse::<i32>() {
+                write_lock.insert(chain_id, chain);
+            }
+        }
+        self.all_chains_cached.store(true, Ordering::Release);
+
+        Ok(write_lock.clone())
+    }
+
     pub async fn get_chain(self: &Self, chain_id: i32) -> Result<Chain> {
         {
             let read_lock = self.cached_chains.read().await;
             let chain = read_lock.get(&chain_id);
             if chain.is_some() {
+                METRICS.observe_cache("chains", true);
                 let chain = chain.unwrap().clone();
                 return Ok(chain);
             }
         }
+        METRICS.observe_cache("chains", false);
         {
             let mut write_lock = self.cached_chains.write().await;
 
-            let res = reqwest::Client::new()
-                .get(format!(
-                    "https://chains.blockscout.com/api/chains/{}",
-                    chain_id
-                ))
-                .send()
+            let (status, body) = self
+                .fetch(
+                    "registry",
+                    &format!("chains/{}", chain_id),
+                    reqwest::Client::new()
+                        .get(format!("{}/{}", self.chains_registry_url, chain_id)),
+                )
                 .await?;
 
-            if res.status() != StatusCode::OK {
-                return Err(anyhow!("request failed: {}", res.status()));
+            if status != StatusCode::OK {
+                return Err(anyhow!("request failed: {}", status));
             }
 
-            let chain: Chain = res.json().await?;
+            let chain: Chain = serde_json::from_slice(&body)?;
             write_lock.insert(chain_id, chain.clone());
 
             Ok(chain)
         }
     }
 
+    pub async fn ping_chains_registry(&self) -> Result<()> {
+        let (status, _) = self
+            .fetch(
+                "registry",
+                "chains/1",
+                reqwest::Client::new().get(format!("{}/1", self.chains_registry_url)),
+            )
+            .await?;
+
+        if status != StatusCode::OK {
+            return Err(anyhow!("request failed: {}", status));
+        }
+        Ok(())
+    }
+
+    async fn get_name_service(&self, chain_id: i32, path: &str) -> Result<Option<Value>> {
+        let path = format!("{}/{}", chain_id, path);
+        let (status, body) = self
+            .fetch(
+                "names",
+                &path,
+                reqwest::Client::new().get(format!("{}/api/v1/{}", self.name_service_url, path)),
+            )
+            .await?;
+        match status {
+            StatusCode::OK => Ok(Some(serde_json::from_slice(&body)?)),
+            StatusCode::NOT_FOUND => Ok(None),
+            status => Err(anyhow!("name service request failed: {}", status)),
+        }
+    }
+
+    // The registry behind the rpc is mainnet's, its names say nothing about other chains.
+    fn ens_rpc(&self, chain_id: i32) -> Option<&EnsRpc> {
+        self.ens_rpc.as_ref().filter(|_| chain_id == ENS_CHAIN_ID)
+    }
+
+    // Returns None when the name is unknown or does not point to an address.
+    pub async fn resolve_name(&self, chain_id: i32, name: &str) -> Result<Option<Address>> {
+        if let Some(ens_rpc) = self.ens_rpc(chain_id) {
+            return ens_rpc.resolve(name).await;
+        }
+        let domain = self
+            .get_name_service(chain_id, &format!("domains/{}", name))
+            .await?;
+        let address = domain
+            .as_ref()
+            .and_then(|domain| domain["resolved_address"]["hash"].as_str());
+        match address {
+            Some(address) => Ok(Some(address.parse().map_err(|e: String| anyhow!(e))?)),
+            None => Ok(None),
+        }
+    }
+
+    // The primary name of the address, lookups are cached for the life of the process.
+    pub async fn lookup_address_name(
+        &self,
+        chain_id: i32,
+        address: &Address,
+    ) -> Result<Option<String>> {
+        let key = (chain_id, address.clone());
+        match self.cached_names.read().await.get(&key) {
+            Some(Ok(name)) => {
+                METRICS.observe_cache("names", true);
+                return Ok(name.clone());
+            }
+            Some(Err(failed_at)) if failed_at.elapsed() < NAME_FAILURE_TTL => {
+                METRICS.observe_cache("names", true);
+                return Err(anyhow!(
+                    "name lookup failed less than {:?} ago",
+                    NAME_FAILURE_TTL
+                ));
+            }
+            _ => {}
+        }
+        METRICS.observe_cache("names", false);
+        let name = match self.ens_rpc(chain_id) {
+            Some(ens_rpc) => ens_rpc.lookup(address).await,
+            None => {
+                self.get_name_service(
+                    chain_id,
+                    &format!("addresses/{}", address.as_str().to_ascii_lowercase()),
+                )
+                .await
+                .map(|data| data.and_then(|data| data["domain"]["name"].as_str().map(String::from)))
+            }
+        };
+        let cached = name.as_ref().map(Clone::clone).map_err(|_| Instant::now());
+        self.cached_names.write().await.insert(key, cached);
+        name
+    }
+
+    // Sends the request, or answers it from the cassette in replay mode.
+    // `scope` and `path` name the recording, the query is taken from the request.
+    async fn fetch(
+        &self,
+        scope: &str,
+        path: &str,
+        req: reqwest::RequestBuilder,
+    ) -> Result<(StatusCode, Vec<u8>)> {
+        // Strip the url from transport errors, it may carry the api key.
+        let (client, req) = req.build_split();
+        let req = req.map_err(|e| e.without_url())?;
+        let cassette = match &self.cassette {
+            Some(cassette) => cassette,
+            None => {
+                let res = client.execute(req).await.map_err(|e| e.without_url())?;
+                let status = res.status();
+                let body = res.bytes().await.map_err(|e| e.without_url())?;
+                return Ok((status, body.to_vec()));
+            }
+        };
+        let request = Cassette::request_key(path, req.url());
+        if cassette.mode() == CassetteMode::Replay {
+            let recording = cassette.replay(scope, &request).await?;
+            let body = match recording.body {
+                Value::String(text) => text.into_bytes(),
+                body => serde_json::to_vec(&body)?,
+            };
+            return Ok((StatusCode::from_u16(recording.status)?, body));
+        }
+        let res = client.execute(req).await.map_err(|e| e.without_url())?;
+        let status = res.status();
+        let body = res.bytes().await.map_err(|e| e.without_url())?.to_vec();
+        let recording = Recording {
+            request,
+            status: status.as_u16(),
+            body: serde_json::from_slice(&body)
+                .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&body).into())),
+        };
+        cassette.record(scope, &recording).await?;
+        Ok((status, body))
+    }
+
     pub async fn get_chain_explorer_url(self: &Self, chain_id: i32) -> Result<String> {
-        if chain_id == 4200 {
-            return Ok("https://scan.merlinverify.com/".into());
+        if let Some(url) = self.explorers.get(&chain_id).and_then(|e| e.url.clone()) {
+            return Ok(url);
         }
         let chain = self.get_chain(chain_id).await?;
         chain.get_url()
@@ -162,20 +559,104 @@ impl API {
         path: impl Into<String>,
         query: &T,
     ) -> Result<Value> {
-        let url = self.get_chain_explorer_url(chain_id).await?;
-        let res = reqwest::Client::new()
-            .get(format!("{}api/v2/{}", url, path.into()))
-            .query(query)
-            .send()
-            .await?;
+        let path = path.into();
+        self.send(chain_id, format!("api/v2/{}", path), path, query)
+            .await
+    }
 
-        if res.status() != StatusCode::OK {
-            return Err(anyhow!("request failed: {}", res.status()));
+    // The Etherscan compatible `/api?module=&action=` routes, only the `result` of a
+    // successful answer is returned. Empty results come with status 0 and count as
+    // success, e.g. "No logs found".
+    pub async fn request_rpc<T: Serialize + ?Sized>(
+        &self,
+        chain_id: i32,
+        module: &str,
+        action: &str,
+        query: &T,
+    ) -> Result<Value> {
+        let mut data = self
+            .send(
+                chain_id,
+                format!("api?module={}&action={}", module, action),
+                format!("rpc/{}/{}", module, action),
+                query,
+            )
+            .await?;
+        match data["result"].take() {
+            result @ Value::Array(_) => Ok(result),
+            result if data["status"] == "1" => Ok(result),
+            result => Err(anyhow!(
+                "{}: {}",
+                data["message"].as_str().unwrap_or("request failed"),
+                result
+            )),
         }
+    }
 
-        let data: Value = res.json().await?;
+    // `endpoint` is relative to the explorer url, `path` names the request in metrics
+    // and recordings.
+    async fn send<T: Serialize + ?Sized>(
+        &self,
+        chain_id: i32,
+        endpoint: String,
+        path: String,
+        query: &T,
+    ) -> Result<Value> {
+        let span = tracing::info_span!(
+            "upstream_request",
+            chain_id,
+            path = %format!("/{}", endpoint),
+            status = tracing::field::Empty,
+            duration_ms = tracing::field::Empty,
+        );
+        async {
+            let url = self.get_chain_explorer_url(chain_id).await?;
+            let mut req = reqwest::Client::new()
+                .get(format!("{}{}", url, endpoint))
+                .query(query);
+            if let Some(explorer) = self.explorers.get(&chain_id) {
+                if let Some(api_key) = &explorer.api_key {
+                    req = req.query(&[("apikey", api_key)]);
+                }
+                for (name, value) in &explorer.headers {
+                    let mut value = HeaderValue::from_str(value)
+                        .map_err(|_| anyhow!("invalid value for header {}", name))?;
+                    value.set_sensitive(true);
+                    req = req.header(name, value);
+                }
+            }
+            let mut trace_headers = HeaderMap::new();
+            inject_trace_context(&mut trace_headers);
+            req = req.headers(trace_headers);
+            let start = Instant::now();
+            let (status, body) = match self.fetch(&chain_id.to_string(), &path, req).await {
+                Ok(res) => res,
+                Err(e) => {
+                    METRICS.observe_upstream_request(chain_id, &path, "error", start.elapsed());
+                    tracing::warn!(
+                        duration_ms = start.elapsed().as_millis() as u64,
+                        "upstream request failed: {}",
+                        e
+                    );
+                    return Err(e);
+                }
+            };
+            METRICS.observe_upstream_request(chain_id, &path, status.as_str(), start.elapsed());
+            let span = tracing::Span::current();
+            span.record("status", status.as_u16());
+            span.record("duration_ms", start.elapsed().as_millis() as u64);
+            tracing::debug!("upstream request finished");
+
+            if status != StatusCode::OK {
+                return Err(StatusError(status).into());
+            }
+
+            let data: Value = serde_json::from_slice(&body)?;
 
-        Ok(data)
+            Ok(data)
+        }
+        .instrument(span)
+        .await
     }
 
     pub async fn search(self: &Self, chain_id: i32, params: SearchParams) -> Result<Value> {
@@ -210,7 +691,7 @@ impl API {
         self.request(chain_id, "stats", &()).await
     }
 
-    pub async fn get_transaction_info(self: &Self, chain_id: i32, hash: String) -> Result<Value> {
+    pub async fn get_transaction_info(self: &Self, chain_id: i32, hash: TxHash) -> Result<Value> {
         self.request(chain_id, format!("transactions/{}", hash), &())
             .await
     }
@@ -218,7 +699,7 @@ impl API {
     pub async fn get_transaction_token_transfers(
         self: &Self,
         chain_id: i32,
-        hash: String,
+        hash: TxHash,
         params: GetTransactionTokenTransfersParams,
     ) -> Result<Value> {
         self.request(
@@ -232,7 +713,7 @@ impl API {
     pub async fn get_transaction_internal_transactions(
         self: &Self,
         chain_id: i32,
-        hash: String,
+        hash: TxHash,
     ) -> Result<Value> {
         self.request(
             chain_id,
@@ -242,7 +723,12 @@ impl API {
         .await
     }
 
-    pub async fn get_transaction_logs(self: &Self, chain_id: i32, hash: String) -> Result<Value> {
+    pub async fn get_transaction_raw_trace(&self, chain_id: i32, hash: TxHash) -> Result<Value> {
+        self.request(chain_id, format!("transactions/{}/raw-trace", hash), &())
+            .await
+    }
+
+    pub async fn get_transaction_logs(self: &Self, chain_id: i32, hash: TxHash) -> Result<Value> {
         self.request(chain_id, format!("transactions/{}/logs", hash), &())
             .await
     }
@@ -250,7 +736,7 @@ impl API {
     pub async fn get_transaction_summary(
         self: &Self,
         chain_id: i32,
-        hash: String,
+        hash: TxHash,
     ) -> Result<Value> {
         self.request(chain_id, format!("transactions/{}/summary", hash), &())
             .await
@@ -259,7 +745,7 @@ impl API {
     pub async fn get_block_info(
         self: &Self,
         chain_id: i32,
-        number_or_hash: String,
+        number_or_hash: BlockId,
     ) -> Result<Value> {
         self.request(chain_id, format!("blocks/{}", number_or_hash), &())
             .await
@@ -268,7 +754,7 @@ impl API {
     pub async fn get_block_transactions(
         self: &Self,
         chain_id: i32,
-        number_or_hash: String,
+        number_or_hash: BlockId,
     ) -> Result<Value> {
         self.request(
             chain_id,
@@ -281,7 +767,7 @@ impl API {
     pub async fn get_block_withdrawals(
         self: &Self,
         chain_id: i32,
-        number_or_hash: String,
+        number_or_hash: BlockId,
     ) -> Result<Value> {
         self.request(
             chain_id,
@@ -295,12 +781,12 @@ impl API {
         self.request(chain_id, "addresses", &()).await
     }
 
-    pub async fn get_address_info(self: &Self, chain_id: i32, hash: String) -> Result<Value> {
+    pub async fn get_address_info(self: &Self, chain_id: i32, hash: Address) -> Result<Value> {
         self.request(chain_id, format!("addresses/{}", hash), &())
             .await
     }
 
-    pub async fn get_address_counters(self: &Self, chain_id: i32, hash: String) -> Result<Value> {
+    pub async fn get_address_counters(self: &Self, chain_id: i32, hash: Address) -> Result<Value> {
         self.request(chain_id, format!("addresses/{}/counters", hash), &())
             .await
     }
@@ -308,7 +794,7 @@ impl API {
     pub async fn get_address_transactions(
         self: &Self,
         chain_id: i32,
-        hash: String,
+        hash: Address,
         params: GetAddressTransactionsParams,
     ) -> Result<Value> {
         self.request(
@@ -322,7 +808,7 @@ impl API {
     pub async fn get_address_token_transfers(
         self: &Self,
         chain_id: i32,
-        hash: String,
+        hash: Address,
         params: GetAddressTokenTransfersParams,
     ) -> Result<Value> {
         self.request(
@@ -336,7 +822,7 @@ impl API {
     pub async fn get_address_internal_transactions(
         self: &Self,
         chain_id: i32,
-        hash: String,
+        hash: Address,
         params: GetAddressInternalTransactionsParams,
     ) -> Result<Value> {
         self.request(
@@ -347,15 +833,19 @@ impl API {
         .await
     }
 
-    pub async fn get_address_logs(self: &Self, chain_id: i32, hash: String) -> Result<Value> {
+    pub async fn get_address_logs(self: &Self, chain_id: i32, hash: Address) -> Result<Value> {
         self.request(chain_id, format!("addresses/{}/logs", hash), &())
             .await
     }
 
+    pub async fn get_logs(&self, chain_id: i32, params: GetLogsParams) -> Result<Value> {
+        self.request_rpc(chain_id, "logs", "getLogs", &params).await
+    }
+
     pub async fn get_address_tokens(
         self: &Self,
         chain_id: i32,
-        hash: String,
+        hash: Address,
         params: GetAddressTokensParams,
     ) -> Result<Value> {
         self.request(chain_id, format!("addresses/{}/tokens", hash), &params)
@@ -365,7 +855,7 @@ impl API {
     pub async fn get_address_coin_balance_history(
         self: &Self,
         chain_id: i32,
-        hash: String,
+        hash: Address,
     ) -> Result<Value> {
         self.request(
             chain_id,
@@ -378,7 +868,7 @@ impl API {
     pub async fn get_address_coin_balance_history_by_day(
         self: &Self,
         chain_id: i32,
-        hash: String,
+        hash: Address,
     ) -> Result<Value> {
         self.request(
             chain_id,
@@ -391,7 +881,7 @@ impl API {
     pub async fn get_address_withdrawals(
         self: &Self,
         chain_id: i32,
-        hash: String,
+        hash: Address,
     ) -> Result<Value> {
         self.request(chain_id, format!("addresses/{}/withdrawals", hash), &())
             .await
@@ -400,7 +890,7 @@ impl API {
     pub async fn get_address_nfts(
         self: &Self,
         chain_id: i32,
-        hash: String,
+        hash: Address,
         params: GetAddressNftsParams,
     ) -> Result<Value> {
         self.request(chain_id, format!("addresses/{}/nft", hash), &params)
@@ -410,7 +900,7 @@ impl API {
     pub async fn get_address_nft_collections(
         self: &Self,
         chain_id: i32,
-        hash: String,
+        hash: Address,
         params: GetAddressNftsParams,
     ) -> Result<Value> {
         self.request(
@@ -421,31 +911,36 @@ impl API {
         .await
     }
 
+    pub async fn get_smart_contract(&self, chain_id: i32, hash: Address) -> Result<Value> {
+        self.request(chain_id, format!("smart-contracts/{}", hash), &())
+            .await
+    }
+
     pub async fn get_tokens(self: &Self, chain_id: i32, params: GetTokensParams) -> Result<Value> {
         self.request(chain_id, "tokens", &params).await
     }
 
-    pub async fn get_token_info(self: &Self, chain_id: i32, hash: String) -> Result<Value> {
+    pub async fn get_token_info(self: &Self, chain_id: i32, hash: Address) -> Result<Value> {
         self.request(chain_id, format!("tokens/{}", hash), &())
             .await
     }
 
-    pub async fn get_token_transfers(self: &Self, chain_id: i32, hash: String) -> Result<Value> {
+    pub async fn get_token_transfers(self: &Self, chain_id: i32, hash: Address) -> Result<Value> {
         self.request(chain_id, format!("tokens/{}/transfers", hash), &())
             .await
     }
 
-    pub async fn get_token_holders(self: &Self, chain_id: i32, hash: String) -> Result<Value> {
+    pub async fn get_token_holders(self: &Self, chain_id: i32, hash: Address) -> Result<Value> {
         self.request(chain_id, format!("tokens/{}/holders", hash), &())
             .await
     }
 
-    pub async fn get_token_counters(self: &Self, chain_id: i32, hash: String) -> Result<Value> {
+    pub async fn get_token_counters(self: &Self, chain_id: i32, hash: Address) -> Result<Value> {
         self.request(chain_id, format!("tokens/{}/counters", hash), &())
             .await
     }
 
-    pub async fn get_token_instances(self: &Self, chain_id: i32, hash: String) -> Result<Value> {
+    pub async fn get_token_instances(self: &Self, chain_id: i32, hash: Address) -> Result<Value> {
         self.request(chain_id, format!("tokens/{}/instances", hash), &())
             .await
     }
@@ -453,7 +948,7 @@ impl API {
     pub async fn get_token_instance_info(
         self: &Self,
         chain_id: i32,
-        hash: String,
+        hash: Address,
         id: u64,
     ) -> Result<Value> {
         self.request(chain_id, format!("tokens/{}/instances/{}", hash, id), &())
@@ -463,7 +958,7 @@ impl API {
     pub async fn get_token_instance_transfers(
         self: &Self,
         chain_id: i32,
-        hash: String,
+        hash: Address,
         id: u64,
     ) -> Result<Value> {
         self.request(
@@ -477,7 +972,7 @@ impl API {
     pub async fn get_token_instance_holders(
         self: &Self,
         chain_id: i32,
-        hash: String,
+        hash: Address,
         id: u64,
     ) -> Result<Value> {
         self.request(
@@ -491,7 +986,7 @@ impl API {
     pub async fn get_token_instance_transfers_count(
         self: &Self,
         chain_id: i32,
-        hash: String,
+        hash: Address,
         id: u64,
     ) -> Result<Value> {
         self.request(
@@ -505,7 +1000,10 @@ impl API {
 
 #[tokio::test]
 async fn test_search() {
-    let api = API::new();
+    let mock = crate::mock_blockscout::MockBlockscout::start()
+        .await
+        .unwrap();
+    let api = mock.api();
     let r = api
         .search(
             1,
@@ -515,6 +1013,263 @@ async fn test_search() {
         )
         .await
         .unwrap();
-    let raw = serde_json::to_string_pretty(&r).unwrap();
-    println!("{}", raw)
+    assert_eq!(r["items"][0]["symbol"], "WETH");
+    assert_eq!(mock.requests(), vec!["1:search?q=WETH"]);
+}
+
+#[tokio::test]
+async fn test_chains_registry() {
+    let mock = crate::mock_blockscout::MockBlockscout::start()
+        .await
+        .unwrap();
+    let api = mock.api();
+    api.ping_chains_registry().await.unwrap();
+
+    let chain = api.get_chain(100).await.unwrap();
+    assert_eq!(chain.get_name(), "Gnosis");
+    assert_eq!(chain.get_url().unwrap(), mock.explorer_url(100));
+    assert!(api.get_chain(999).await.is_err());
+
+    let chains = api.get_chains().await.unwrap();
+    assert_eq!(chains.len(), 4);
+    assert_eq!(chains[&11155111].get_name(), "Sepolia");
+    assert_eq!(
+        api.get_chain_explorer_url(4200).await.unwrap(),
+        mock.explorer_url(4200)
+    );
+}
+
+#[tokio::test]
+async fn test_api_methods() {
+    let mock = crate::mock_blockscout::MockBlockscout::start()
+        .await
+        .unwrap();
+    let api = mock.api_with_explorers(HashMap::from([(
+        100,
+        ExplorerConfig {
+            api_key: Some("secret".into()),
+            ..Default::default()
+        },
+    )]));
+    let address: Address = "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045"
+        .parse()
+        .unwrap();
+    let token: Address = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"
+        .parse()
+        .unwrap();
+    let nft: Address = "0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D"
+        .parse()
+        .unwrap();
+    let tx: TxHash = "0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060"
+        .parse()
+        .unwrap();
+    let block: TxHash = "0x88e96d4537bea4d9c05d12549907b32561d3bf31f45aae734cdc119f13406cb6"
+        .parse()
+        .unwrap();
+    let first = |v: Value| v["items"][0].clone();
+
+    let r = api.get_transactions(1, Default::default()).await.unwrap();
+    assert_eq!(first(r)["hash"], tx.as_str());
+    let r = api.get_blocks(1, Default::default()).await.unwrap();
+    assert_eq!(first(r)["height"], 19000000);
+    let r = api.get_transfers(1).await.unwrap();
+    assert_eq!(first(r)["token"]["symbol"], "WETH");
+    let r = api.get_internal_transactions(1).await.unwrap();
+    assert_eq!(first(r)["type"], "call");
+    let r = api.get_withdrawals(1).await.unwrap();
+    assert_eq!(first(r)["validator_index"], 412003);
+    let r = api.get_stats(1).await.unwrap();
+    assert_eq!(r["total_blocks"], "19000001");
+
+    let r = api.get_transaction_info(1, tx.clone()).await.unwrap();
+    assert_eq!(r["method"], "deposit");
+    let r = api
+        .get_transaction_token_transfers(1, tx.clone(), Default::default())
+        .await
+        .unwrap();
+    assert_eq!(first(r)["log_index"], 112);
+    let r = api
+        .get_transaction_internal_transactions(1, tx.clone())
+        .await
+        .unwrap();
+    assert_eq!(first(r)["index"], 1);
+    let r = api.get_transaction_logs(1, tx.clone()).await.unwrap();
+    assert_eq!(first(r)["decoded"]["method_id"], "e1fffcc4");
+    let r = api.get_transaction_summary(1, tx.clone()).await.unwrap();
+    assert_eq!(r["success"], true);
+    let r = api.get_transaction_raw_trace(1, tx.clone()).await.unwrap();
+    assert_eq!(r[0]["action"]["input"], "0xd0e30db0");
+
+    for number_or_hash in [BlockId::Number(19000000), BlockId::Hash(block.clone())] {
+        let r = api.get_block_info(1, number_or_hash.clone()).await.unwrap();
+        assert_eq!(r["hash"], block.as_str());
+        let r = api
+            .get_block_transactions(1, number_or_hash.clone())
+            .await
+            .unwrap();
+        assert_eq!(first(r)["block_number"], 19000000);
+        let r = api.get_block_withdrawals(1, number_or_hash).await.unwrap();
+        assert_eq!(first(r)["block_number"], 19000000);
+    }
+
+    let r = api.get_addresses(1).await.unwrap();
+    assert_eq!(first(r)["name"], "Beacon Deposit Contract");
+    let r = api.get_address_info(1, address.clone()).await.unwrap();
+    assert_eq!(r["ens_domain_name"], "vitalik.eth");
+    let r = api.get_address_counters(1, address.clone()).await.unwrap();
+    assert_eq!(r["transactions_count"], "1021");
+    let r = api
+        .get_address_transactions(1, address.clone(), Default::default())
+        .await
+        .unwrap();
+    assert_eq!(first(r)["from"]["hash"], address.as_str());
+    let r = api
+        .get_address_token_transfers(1, address.clone(), Default::default())
+        .await
+        .unwrap();
+    assert_eq!(first(r)["to"]["hash"], address.as_str());
+    let r = api
+        .get_address_internal_transactions(1, address.clone(), Default::default())
+        .await
+        .unwrap();
+    assert_eq!(first(r)["to"]["hash"], address.as_str());
+    let r = api.get_address_logs(1, address.clone()).await.unwrap();
+    assert_eq!(first(r)["address"]["hash"], token.as_str());
+    let r = api
+        .get_address_tokens(1, address.clone(), Default::default())
+        .await
+        .unwrap();
+    assert_eq!(r["items"][1]["token"]["symbol"], "USDC");
+    let r = api
+        .get_address_coin_balance_history(1, address.clone())
+        .await
+        .unwrap();
+    assert_eq!(first(r)["transaction_hash"], tx.as_str());
+    let r = api
+        .get_address_coin_balance_history_by_day(1, address.clone())
+        .await
+        .unwrap();
+    assert_eq!(r["days"], 10);
+    let r = api
+        .get_address_withdrawals(1, address.clone())
+        .await
+        .unwrap();
+    assert_eq!(first(r)["receiver"]["hash"], address.as_str());
+    let r = api
+        .get_address_nfts(1, address.clone(), Default::default())
+        .await
+        .unwrap();
+    assert_eq!(first(r)["token"]["symbol"], "BAYC");
+    let r = api
+        .get_address_nft_collections(1, address.clone(), Default::default())
+        .await
+        .unwrap();
+    assert_eq!(first(r)["amount"], "1");
+
+    let r = api
+        .get_tokens(
+            1,
+            GetTokensParams {
+                q: "USD".into(),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(r["items"][1]["symbol"], "USDC");
+    let r = api.get_token_info(1, token.clone()).await.unwrap();
+    assert_eq!(r["decimals"], "18");
+    let r = api.get_smart_contract(1, token.clone()).await.unwrap();
+    assert_eq!(r["name"], "WETH9");
+    let r = api.get_token_transfers(1, token.clone()).await.unwrap();
+    assert_eq!(first(r)["type"], "token_minting");
+    let r = api.get_token_holders(1, token.clone()).await.unwrap();
+    assert_eq!(first(r)["address"]["hash"], address.as_str());
+    let r = api.get_token_counters(1, token.clone()).await.unwrap();
+    assert_eq!(r["token_holders_count"], "1093842");
+    let r = api.get_token_instances(1, nft.clone()).await.unwrap();
+    assert_eq!(first(r)["id"], "42");
+    let r = api
+        .get_token_instance_info(1, nft.clone(), 42)
+        .await
+        .unwrap();
+    assert_eq!(r["owner"]["hash"], address.as_str());
+    let r = api
+        .get_token_instance_transfers(1, nft.clone(), 42)
+        .await
+        .unwrap();
+    assert_eq!(first(r)["total"]["token_id"], "42");
+    let r = api
+        .get_token_instance_holders(1, nft.clone(), 42)
+        .await
+        .unwrap();
+    assert_eq!(first(r)["value"], "1");
+    let r = api
+        .get_token_instance_transfers_count(1, nft.clone(), 42)
+        .await
+        .unwrap();
+    assert_eq!(r["transfers_count"], 7);
+
+    // Configured explorers get the api key, unknown paths surface the upstream status.
+    api.get_stats(100).await.unwrap();
+    assert!(
+        mock.requests()
+            .contains(&"100:stats?apikey=secret".to_string())
+    );
+    assert!(api.get_stats(4200).await.is_ok());
+    let e = api
+        .request(1, "unknown", &())
+        .await
+        .unwrap_err()
+        .to_string();
+    assert_eq!(e, "request failed: 404 Not Found");
+}
+
+#[tokio::test]
+async fn test_errors_hide_api_key() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // Answers with a truncated body, so reading it fails after the status.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut buf = [0u8; 1024];
+            let _ = stream.read(&mut buf).await;
+            let _ = stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 100\r\n\r\n{")
+                .await;
+        }
+    });
+    let explorer = |url: String| ExplorerConfig {
+        url: Some(url),
+        api_key: Some("secret".into()),
+        ..Default::default()
+    };
+    let api = API::with_explorers(HashMap::from([
+        (1, explorer(url)),
+        (2, explorer("http://127.0.0.1:1/".into())),
+    ]));
+    for chain_id in [1, 2] {
+        let e = api.get_stats(chain_id).await.unwrap_err();
+        assert!(!format!("{}", e).contains("secret"), "{}", e);
+        assert!(!format!("{:?}", e).contains("secret"), "{:?}", e);
+    }
+}
+
+#[test]
+fn test_explorer_config() {
+    let explorers: HashMap<i32, ExplorerConfig> = serde_json::from_str(
+        r#"{"1": {"api_key": "secret"}, "100": {"url": "https://gnosis.example", "headers": {"x-token": "secret"}}}"#,
+    )
+    .unwrap();
+    let api = API::with_explorers(explorers);
+    assert_eq!(api.configured_chain_ids(), vec![1, 100]);
+    assert_eq!(api.explorer_chain_ids(), vec![1, 100, 4200]);
+    assert_eq!(
+        api.explorers[&100].url.as_deref(),
+        Some("https://gnosis.example/")
+    );
+    assert!(!format!("{:?}", api.explorers[&1]).contains("secret"));
+    assert!(!format!("{:?}", api.explorers[&100]).contains("secret"));
 }
@@ -0,0 +1,340 @@
+use crate::abi;
+use crate::block_scout_api::API;
+use crate::types::{Address, TxHash};
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+const MAX_ABI_LOOKUPS: usize = 10;
+
+#[derive(Serialize, Debug, Default)]
+pub struct CallNode {
+    // call, delegatecall, staticcall, create...
+    pub call_type: String,
+    pub from: Option<Address>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_name: Option<String>,
+    pub to: Option<Address>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to_name: Option<String>,
+    // In wei, as a decimal string.
+    pub value: String,
+    pub gas: Option<u64>,
+    pub gas_used: Option<u64>,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selector: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub calls: Vec<CallNode>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CallTree {
+    pub transaction_hash: TxHash,
+    // `raw_trace`, or `internal_transactions` when the explorer has no trace and the
+    // calls below the root are flat.
+    pub source: &'static str,
+    pub root: CallNode,
+}
+
+impl CallNode {
+    fn walk<'a>(&'a self, nodes: &mut Vec<&'a CallNode>) {
+        nodes.push(self);
+        for call in &self.calls {
+            call.walk(nodes);
+        }
+    }
+
+    fn walk_mut(&mut self, f: &mut impl FnMut(&mut CallNode)) {
+        f(self);
+        for call in &mut self.calls {
+            call.walk_mut(f);
+        }
+    }
+
+    // Depth first, the order the calls were made in.
+    pub fn flatten(&self) -> Vec<&CallNode> {
+        let mut nodes = Vec::new();
+        self.walk(&mut nodes);
+        nodes
+    }
+}
+
+// Traces report quantities as hex, the explorer as decimal strings.
+fn quantity(value: &Value) -> Option<u128> {
+    match value {
+        Value::String(value) => match value.strip_prefix("0x") {
+            Some(hex) => u128::from_str_radix(hex, 16).ok(),
+            None => value.parse().ok(),
+        },
+        Value::Number(value) => value.as_u64().map(u128::from),
+        _ => None,
+    }
+}
+
+fn address(value: &Value) -> Option<Address> {
+    value
+        .as_str()
+        .or_else(|| value["hash"].as_str())?
+        .parse()
+        .ok()
+}
+
+fn call_node(
+    call_type: &str,
+    action: &Value,
+    result: &Value,
+    to: &Value,
+    error: &Value,
+) -> CallNode {
+    CallNode {
+        call_type: call_type.to_ascii_lowercase(),
+        from: address(&action["from"]),
+        to: address(to),
+        value: quantity(&action["value"]).unwrap_or_default().to_string(),
+        gas: quantity(&action["gas"]).and_then(|gas| gas.try_into().ok()),
+        gas_used: quantity(&result["gasUsed"]).and_then(|gas| gas.try_into().ok()),
+        success: error.is_null(),
+        error: error.as_str().map(String::from),
+        selector: action["input"].as_str().and_then(abi::calldata_selector),
+        ..Default::default()
+    }
+}
+
+// Parity style traces, a flat list where `traceAddress` is the path from the root.
+fn from_parity_trace(entries: &[Value]) -> Option<CallNode> {
+    let mut entries: Vec<(&[Value], CallNode)> = entries
+        .iter()
+        .filter_map(|entry| {
+            let path = entry["traceAddress"].as_array()?.as_slice();
+            let action = &entry["action"];
+            let result = &entry["result"];
+            let (call_type, to) = match entry["type"].as_str()? {
+                "create" => ("create", &result["address"]),
+                "suicide" => ("selfdestruct", &action["refundAddress"]),
+                _ => (action["callType"].as_str().unwrap_or("call"), &action["to"]),
+            };
+            Some((
+                path,
+                call_node(call_type, action, result, to, &entry["error"]),
+            ))
+        })
+        .collect();
+    entries.sort_by_key(|(path, _)| path.iter().filter_map(Value::as_u64).collect::<Vec<_>>());
+    let mut entries = entries.into_iter();
+    let (_, mut root) = entries.next()?;
+    for (path, node) in entries {
+        // A gap in the trace hangs the call off its deepest known ancestor.
+        let mut parent = &mut root;
+        for index in &path[..path.len().saturating_sub(1)] {
+            let index = index.as_u64().unwrap_or_default() as usize;
+            if index >= parent.calls.len() {
+                break;
+            }
+            parent = &mut parent.calls[index];
+        }
+        parent.calls.push(node);
+    }
+    Some(root)
+}
+
+// Geth `callTracer` output, already nested under `calls`.
+fn from_geth_trace(frame: &Value) -> Option<CallNode> {
+    let call_type = frame["type"].as_str()?;
+    let mut node = call_node(call_type, frame, frame, &frame["to"], &frame["error"]);
+    if let Some(calls) = frame["calls"].as_array() {
+        node.calls = calls.iter().filter_map(from_geth_trace).collect();
+    }
+    Some(node)
+}
+
+fn from_raw_trace(trace: &Value) -> Option<CallNode> {
+    match trace {
+        Value::Array(entries) if entries.first()?.get("traceAddress").is_some() => {
+            from_parity_trace(entries)
+        }
+        Value::Array(entries) if entries.len() == 1 => from_geth_trace(&entries[0]),
+        Value::Object(_) => from_geth_trace(trace),
+        _ => None,
+    }
+}
+
+// The transaction itself as the root, its internal transactions flat below it.
+fn from_internal_transactions(info: &Value, internal: &Value) -> CallNode {
+    let mut root = CallNode {
+        call_type: "call".into(),
+        from: address(&info["from"]),
+        to: address(&info["to"]).or_else(|| address(&info["created_contract"])),
+        value: quantity(&info["value"]).unwrap_or_default().to_string(),
+        gas: quantity(&info["gas_limit"]).and_then(|gas| gas.try_into().ok()),
+        gas_used: quantity(&info["gas_used"]).and_then(|gas| gas.try_into().ok()),
+        success: info["result"]
+            .as_str()
+            .is_none_or(|result| result == "success"),
+        error: info["revert_reason"].as_str().map(String::from),
+        selector: info["raw_input"]
+            .as_str()
+            .and_then(abi::calldata_selector)
+            .or_else(|| {
+                info["decoded_input"]["method_id"]
+                    .as_str()
+                    .map(String::from)
+            }),
+        ..Default::default()
+    };
+    if let Some(items) = internal["items"].as_array() {
+        root.calls = items
+            .iter()
+            .map(|item| CallNode {
+                call_type: item["type"].as_str().unwrap_or("call").into(),
+                from: address(&item["from"]),
+                to: address(&item["to"]).or_else(|| address(&item["created_contract"])),
+                value: quantity(&item["value"]).unwrap_or_default().to_string(),
+                gas: quantity(&item["gas_limit"]).and_then(|gas| gas.try_into().ok()),
+                success: item["success"].as_bool().unwrap_or(true),
+                error: item["error"].as_str().map(String::from),
+                ..Default::default()
+            })
+            .collect();
+    }
+    root
+}
+
+// Names the explorer attached to the addresses of the transaction and its calls.
+fn collect_names(value: &Value, names: &mut HashMap<Address, String>) {
+    match value {
+        Value::Object(object) => {
+            let name = object
+                .get("ens_domain_name")
+                .and_then(Value::as_str)
+                .or_else(|| object.get("name").and_then(Value::as_str));
+            if let (Some(address), Some(name)) = (object.get("hash").and_then(address), name) {
+                names.entry(address).or_insert_with(|| name.to_string());
+            }
+            for value in object.values() {
+                collect_names(value, names);
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                collect_names(value, names);
+            }
+        }
+        _ => {}
+    }
+}
+
+// Selectors are named from the ABIs of verified callees, at most `MAX_ABI_LOOKUPS`
+// of them. Unverified contracts keep just the selector.
+async fn decode_methods(api: &API, chain_id: i32, root: &mut CallNode) {
+    let mut callees = Vec::<Address>::new();
+    for node in root.flatten() {
+        if let (Some(to), Some(_)) = (&node.to, &node.selector)
+            && !callees.contains(to)
+            && callees.len() < MAX_ABI_LOOKUPS
+        {
+            callees.push(to.clone());
+        }
+    }
+    let lookups = callees.into_iter().map(|callee| async move {
+        let abi = api
+            .get_smart_contract(chain_id, callee.clone())
+            .await
+            .map(|contract| contract["abi"].clone());
+        (callee, abi)
+    });
+    let mut abis = HashMap::new();
+    for (callee, abi) in futures::future::join_all(lookups).await {
+        match abi {
+            Ok(abi) if abi.is_array() => {
+                abis.insert(callee, abi);
+            }
+            Ok(_) => {}
+            Err(e) => tracing::debug!("no abi for {}: {}", callee, e),
+        }
+    }
+    root.walk_mut(&mut |node| {
+        if let (Some(to), Some(selector)) = (&node.to, &node.selector)
+            && let Some(abi) = abis.get(to)
+        {
+            node.method = abi::find_function(abi, selector);
+        }
+    });
+}
+
+pub async fn call_tree(api: &API, chain_id: i32, hash: TxHash) -> Result<CallTree> {
+    let (info, internal, trace) = tokio::join!(
+        api.get_transaction_info(chain_id, hash.clone()),
+        api.get_transaction_internal_transactions(chain_id, hash.clone()),
+        api.get_transaction_raw_trace(chain_id, hash.clone()),
+    );
+    let info = info?;
+    let internal = internal.unwrap_or_else(|e| {
+        tracing::debug!("no internal transactions for {}: {}", hash, e);
+        Value::Null
+    });
+    let (source, mut root) = match trace.as_ref().ok().and_then(from_raw_trace) {
+        Some(root) => ("raw_trace", root),
+        None => (
+            "internal_transactions",
+            from_internal_transactions(&info, &internal),
+        ),
+    };
+    let mut names = HashMap::new();
+    collect_names(&info, &mut names);
+    collect_names(&internal, &mut names);
+    root.walk_mut(&mut |node| {
+        node.from_name = node.from.as_ref().and_then(|a| names.get(a)).cloned();
+        node.to_name = node.to.as_ref().and_then(|a| names.get(a)).cloned();
+    });
+    decode_methods(api, chain_id, &mut root).await;
+    Ok(CallTree {
+        transaction_hash: hash,
+        source,
+        root,
+    })
+}
+
+#[tokio::test]
+async fn test_call_tree() {
+    let mock = crate::mock_blockscout::MockBlockscout::start()
+        .await
+        .unwrap();
+    let api = mock.api();
+    let hash: TxHash = "0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060"
+        .parse()
+        .unwrap();
+
+    let tree = call_tree(&api, 1, hash).await.unwrap();
+    assert_eq!(tree.source, "raw_trace");
+    let root = &tree.root;
+    assert_eq!(root.method.as_deref(), Some("deposit()"));
+    assert_eq!(root.value, "1000000000000000000");
+    assert_eq!(root.gas_used, Some(45038));
+    assert_eq!(root.to_name.as_deref(), Some("WETH9"));
+    assert_eq!(root.calls.len(), 2);
+    let nested = &root.calls[0].calls[0];
+    assert_eq!(nested.call_type, "delegatecall");
+    assert!(!nested.success);
+    assert_eq!(nested.error.as_deref(), Some("Reverted"));
+    assert_eq!(nested.selector.as_deref(), Some("0x70a08231"));
+    assert_eq!(nested.method, None);
+    assert_eq!(root.calls[1].method.as_deref(), Some("totalSupply()"));
+    assert_eq!(root.flatten().len(), 4);
+    let graph = crate::render::Graph::from_call_tree(&tree);
+    assert_eq!(graph.edges[0].label, "#0 call deposit() 1 native");
+    assert_eq!(graph.edges[2].label, "#2 delegatecall 0x70a08231 (failed)");
+
+    // Without a trace the internal transactions hang flat off the transaction.
+    let unknown = format!("0x{}", "ab".repeat(32));
+    let tree = call_tree(&api, 1, unknown.parse().unwrap()).await.unwrap();
+    assert_eq!(tree.source, "internal_transactions");
+    assert_eq!(tree.root.calls.len(), 1);
+    assert_eq!(tree.root.calls[0].gas, Some(2300));
+    assert_eq!(tree.root.method.as_deref(), Some("deposit()"));
+}
@@ -0,0 +1,171 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+
+pub const CASSETTE_DIR_ENV: &str = "BLOCKSCOUT_CASSETTE_DIR";
+pub const CASSETTE_MODE_ENV: &str = "BLOCKSCOUT_CASSETTE_MODE";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CassetteMode {
+    // Send requests upstream and save every response.
+    Record,
+    // Never touch the network, unrecorded requests are errors.
+    Replay,
+}
+
+// A directory of recorded upstream exchanges, one json file per request at
+// `<scope>/<path>[@<query>].json` where scope is the chain id or `registry`.
+// The `apikey` query parameter and configured headers are never written.
+#[derive(Clone, Debug)]
+pub struct Cassette {
+    dir: PathBuf,
+    mode: CassetteMode,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Recording {
+    pub request: String,
+    pub status: u16,
+    pub body: Value,
+}
+
+impl Cassette {
+    pub fn new(dir: impl Into<PathBuf>, mode: CassetteMode) -> Self {
+        Cassette {
+            dir: dir.into(),
+            mode,
+        }
+    }
+
+    pub fn from_env() -> Result<Option<Self>> {
+        let mode = match std::env::var(CASSETTE_MODE_ENV).as_deref() {
+            Ok("record") => CassetteMode::Record,
+            Ok("replay") => CassetteMode::Replay,
+            Ok("") | Ok("off") | Err(_) => return Ok(None),
+            Ok(mode) => {
+                return Err(anyhow!(
+                    "{} must be record, replay or off, got {}",
+                    CASSETTE_MODE_ENV,
+                    mode
+                ));
+            }
+        };
+        let dir = std::env::var(CASSETTE_DIR_ENV)
+            .map_err(|_| anyhow!("{} is required by {}", CASSETTE_DIR_ENV, CASSETTE_MODE_ENV))?;
+        Ok(Some(Self::new(dir, mode)))
+    }
+
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    // The request as recorded, the query without credentials.
+    pub fn request_key(path: &str, url: &reqwest::Url) -> String {
+        let query: Vec<String> = url
+            .query_pairs()
+            .filter(|(name, _)| name != "apikey")
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+        if query.is_empty() {
+            path.to_string()
+        } else {
+            format!("{}?{}", path, query.join("&"))
+        }
+    }
+
+    fn file(&self, scope: &str, request: &str) -> Result<PathBuf> {
+        let (path, query) = match request.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (request, None),
+        };
+        if path
+            .split('/')
+            .any(|segment| segment.is_empty() || segment == "." || segment == "..")
+        {
+            return Err(anyhow!("can not record request {}", request));
+        }
+        let mut name = path.to_string();
+        if let Some(query) = query {
+            name.push('@');
+            name.extend(query.chars().map(|c| {
+                if c.is_ascii_alphanumeric() || "-_.=&".contains(c) {
+                    c
+                } else {
+                    '_'
+                }
+            }));
+        }
+        Ok(self.dir.join(scope).join(format!("{}.json", name)))
+    }
+
+    pub async fn replay(&self, scope: &str, request: &str) -> Result<Recording> {
+        let file = self.file(scope, request)?;
+        let content = tokio::fs::read_to_string(&file).await.map_err(|_| {
+            anyhow!(
+                "no recording for {} {} in cassette {}",
+                scope,
+                request,
+                self.dir.display()
+            )
+        })?;
+        serde_json::from_str(&content)
+            .map_err(|e| anyhow!("invalid recording {}: {}", file.display(), e))
+    }
+
+    pub async fn record(&self, scope: &str, recording: &Recording) -> Result<()> {
+        let file = self.file(scope, &recording.request)?;
+        if let Some(parent) = file.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&file, serde_json::to_vec_pretty(recording)?).await?;
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_record_and_replay() {
+    use crate::block_scout_api::{ExplorerConfig, SearchParams};
+    use crate::mock_blockscout::MockBlockscout;
+    use std::collections::HashMap;
+
+    let dir = std::env::temp_dir().join(format!("blocks_mcp_cassette_{}", std::process::id()));
+    let mock = MockBlockscout::start().await.unwrap();
+    let explorers = HashMap::from([(
+        1,
+        ExplorerConfig {
+            api_key: Some("secret".into()),
+            ..Default::default()
+        },
+    )]);
+    let recorder = mock
+        .api_with_explorers(explorers.clone())
+        .with_cassette(Cassette::new(&dir, CassetteMode::Record));
+    let search = || SearchParams { q: "WETH".into() };
+    let recorded = recorder.search(1, search()).await.unwrap();
+    recorder.get_chain(100).await.unwrap();
+    assert!(recorder.request(1, "unknown", &()).await.is_err());
+
+    let file = dir.join("1/search@q=WETH.json");
+    assert!(!std::fs::read_to_string(&file).unwrap().contains("secret"));
+    drop(mock);
+
+    let explorers = HashMap::from([(
+        1,
+        ExplorerConfig {
+            url: Some("http://127.0.0.1:9".into()),
+            ..Default::default()
+        },
+    )]);
+    let player = crate::block_scout_api::API::with_explorers(explorers)
+        .with_chains_registry("http://127.0.0.1:9")
+        .with_cassette(Cassette::new(&dir, CassetteMode::Replay));
+    assert_eq!(player.search(1, search()).await.unwrap(), recorded);
+    assert_eq!(player.get_chain(100).await.unwrap().get_name(), "Gnosis");
+    let e = player.request(1, "unknown", &()).await.unwrap_err();
+    assert_eq!(e.to_string(), "request failed: 404 Not Found");
+    let e = player.get_stats(1).await.unwrap_err();
+    assert!(e.to_string().starts_with("no recording for 1 stats"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,131 @@
+use crate::block_scout_api::{API, MERLIN_CHAIN_ID, SearchParams};
+use rmcp::{
+    ErrorData as McpError,
+    model::{CompleteRequestParam, CompleteResult, CompletionInfo},
+};
+use serde_json::Value;
+
+const MAX_VALUES: usize = 100;
+
+// The completion request of this protocol revision carries no sibling
+// arguments, so token and address lookups run against this chain.
+const DEFAULT_CHAIN_ID: i32 = 1;
+
+fn completion(values: Vec<String>) -> CompleteResult {
+    let total = values.len();
+    let values: Vec<String> = values.into_iter().take(MAX_VALUES).collect();
+    CompleteResult {
+        completion: CompletionInfo {
+            has_more: Some(total > values.len()),
+            total: Some(total as u32),
+            values,
+        },
+    }
+}
+
+fn match_chains(chains: Vec<(i32, String)>, value: &str) -> Vec<String> {
+    let value = value.trim().to_lowercase();
+    let mut chains: Vec<(i32, String)> = chains
+        .into_iter()
+        .filter(|(chain_id, name)| {
+            chain_id.to_string().starts_with(&value) || name.to_lowercase().contains(&value)
+        })
+        .collect();
+    chains.sort_by_key(|(chain_id, _)| *chain_id);
+    chains
+        .into_iter()
+        .map(|(chain_id, _)| chain_id.to_string())
+        .collect()
+}
+
+fn match_search_items(data: &Value, tokens_only: bool) -> Vec<String> {
+    let mut values = Vec::<String>::new();
+    let items = data.get("items").and_then(Value::as_array);
+    for item in items.into_iter().flatten() {
+        let typ = item.get("type").and_then(Value::as_str).unwrap_or_default();
+        if tokens_only && typ != "token" {
+            continue;
+        }
+        let address = item
+            .get("address_hash")
+            .or_else(|| item.get("address"))
+            .and_then(Value::as_str);
+        if let Some(address) = address
+            && !values.iter().any(|v| v == address)
+        {
+            values.push(address.to_string());
+        }
+    }
+    values
+}
+
+async fn complete_chain_id(api: &API, value: &str) -> anyhow::Result<Vec<String>> {
+    let mut chains: Vec<(i32, String)> = api
+        .get_chains()
+        .await?
+        .into_iter()
+        .map(|(chain_id, chain)| (chain_id, chain.get_name().to_string()))
+        .collect();
+    if !chains
+        .iter()
+        .any(|(chain_id, _)| *chain_id == MERLIN_CHAIN_ID)
+    {
+        chains.push((MERLIN_CHAIN_ID, "Merlin".into()));
+    }
+    Ok(match_chains(chains, value))
+}
+
+async fn complete_address(
+    api: &API,
+    value: &str,
+    tokens_only: bool,
+) -> anyhow::Result<Vec<String>> {
+    let q = value.trim();
+    if q.is_empty() {
+        return Ok(vec![]);
+    }
+    let data = api
+        .search(DEFAULT_CHAIN_ID, SearchParams { q: q.into() })
+        .await?;
+    Ok(match_search_items(&data, tokens_only))
+}
+
+pub async fn complete(
+    api: &API,
+    request: CompleteRequestParam,
+) -> Result<CompleteResult, McpError> {
+    let value = request.argument.value.as_str();
+    let rst = match request.argument.name.as_str() {
+        "chain_id" => complete_chain_id(api, value).await,
+        "token_address" => complete_address(api, value, true).await,
+        "address_hash" => complete_address(api, value, false).await,
+        _ => Ok(vec![]),
+    };
+    match rst {
+        Ok(values) => Ok(completion(values)),
+        Err(e) => Err(McpError::internal_error(e.to_string(), None)),
+    }
+}
+
+#[test]
+fn test_match_chains() {
+    let chains = vec![
+        (10, "OP Mainnet".to_string()),
+        (1, "Ethereum".to_string()),
+        (100, "Gnosis".to_string()),
+        (4200, "Merlin".to_string()),
+    ];
+    assert_eq!(match_chains(chains.clone(), "1"), vec!["1", "10", "100"]);
+    assert_eq!(match_chains(chains.clone(), "eth"), vec!["1"]);
+    assert_eq!(match_chains(chains, "").len(), 4);
+
+    let data = serde_json::json!({
+        "items": [
+            {"type": "token", "address_hash": "0xc02a"},
+            {"type": "address", "address_hash": "0x0001"},
+            {"type": "transaction", "transaction_hash": "0xdead"},
+        ]
+    });
+    assert_eq!(match_search_items(&data, true), vec!["0xc02a"]);
+    assert_eq!(match_search_items(&data, false), vec!["0xc02a", "0x0001"]);
+}
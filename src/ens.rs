@@ -0,0 +1,227 @@
+use crate::abi::{from_hex, to_hex};
+use crate::types::{Address, keccak256};
+use anyhow::{Result, anyhow};
+use serde_json::{Value, json};
+
+pub const ENS_RPC_URL_ENV: &str = "ENS_RPC_URL";
+pub const ENS_CHAIN_ID: i32 = 1;
+
+// The ENS registry, deployed at the same address on mainnet and its testnets.
+const ENS_REGISTRY: &str = "0x00000000000C2E074eC69A0dFb2997BA6C7d2e1e";
+
+const RESOLVER_SELECTOR: &str = "0178b8bf";
+const ADDR_SELECTOR: &str = "3b3b57de";
+const NAME_SELECTOR: &str = "691f3431";
+
+pub fn namehash(name: &str) -> [u8; 32] {
+    let mut node = [0u8; 32];
+    if name.is_empty() {
+        return node;
+    }
+    for label in name.rsplit('.') {
+        let mut data = node.to_vec();
+        data.extend_from_slice(&keccak256(label.as_bytes()));
+        node = keccak256(&data);
+    }
+    node
+}
+
+// The last 20 bytes of the first returned word, None for the zero address.
+fn decode_address(data: &[u8]) -> Result<Option<Address>> {
+    if data.len() < 32 {
+        return Ok(None);
+    }
+    let address = &data[12..32];
+    if address.iter().all(|b| *b == 0) {
+        return Ok(None);
+    }
+    Ok(Some(
+        to_hex(address).parse().map_err(|e: String| anyhow!(e))?,
+    ))
+}
+
+fn decode_string(data: &[u8]) -> Result<Option<String>> {
+    // Offsets come from a resolver the record owner controls.
+    let word = |at: usize| -> Result<usize> {
+        let bytes = at
+            .checked_add(32)
+            .and_then(|end| data.get(at..end))
+            .ok_or_else(|| anyhow!("truncated abi string"))?;
+        Ok(bytes[24..]
+            .iter()
+            .fold(0usize, |n, b| (n << 8) | *b as usize))
+    };
+    if data.is_empty() {
+        return Ok(None);
+    }
+    let offset = word(0)?;
+    let len = word(offset)?;
+    let bytes = offset
+        .checked_add(32)
+        .and_then(|start| data.get(start..start.checked_add(len)?))
+        .ok_or_else(|| anyhow!("truncated abi string"))?;
+    let name = String::from_utf8(bytes.to_vec())?;
+    Ok((!name.is_empty()).then_some(name))
+}
+
+// Resolves names against the ENS registry with plain `eth_call`s, for deployments
+// that would rather trust their own node than the Blockscout name service.
+#[derive(Clone, Debug)]
+pub struct EnsRpc {
+    url: String,
+}
+
+impl EnsRpc {
+    pub fn new(url: &str) -> Self {
+        EnsRpc { url: url.into() }
+    }
+
+    async fn call(&self, to: &str, selector: &str, node: &[u8; 32]) -> Result<Vec<u8>> {
+        let res: Value = reqwest::Client::new()
+            .post(&self.url)
+            .json(&json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "eth_call",
+                "params": [{"to": to, "data": format!("0x{}{}", selector, &to_hex(node)[2..])}, "latest"],
+            }))
+            .send()
+            .await
+            .map_err(|e| e.without_url())?
+            .json()
+            .await?;
+        if let Some(error) = res.get("error") {
+            return Err(anyhow!("ens rpc call failed: {}", error));
+        }
+        let result = res["result"].as_str().unwrap_or_default();
+        from_hex(result).ok_or_else(|| anyhow!("invalid hex {}", result))
+    }
+
+    async fn resolver(&self, node: &[u8; 32]) -> Result<Option<Address>> {
+        decode_address(&self.call(ENS_REGISTRY, RESOLVER_SELECTOR, node).await?)
+    }
+
+    pub async fn resolve(&self, name: &str) -> Result<Option<Address>> {
+        let node = namehash(name);
+        let Some(resolver) = self.resolver(&node).await? else {
+            return Ok(None);
+        };
+        decode_address(&self.call(resolver.as_str(), ADDR_SELECTOR, &node).await?)
+    }
+
+    // The primary name of the address, only if it resolves back to the same address.
+    pub async fn lookup(&self, address: &Address) -> Result<Option<String>> {
+        let reverse = format!(
+            "{}.addr.reverse",
+            address.as_str()[2..].to_ascii_lowercase()
+        );
+        let node = namehash(&reverse);
+        let Some(resolver) = self.resolver(&node).await? else {
+            return Ok(None);
+        };
+        let data = self.call(resolver.as_str(), NAME_SELECTOR, &node).await?;
+        let Some(name) = decode_string(&data)? else {
+            return Ok(None);
+        };
+        match self.resolve(&name).await? {
+            Some(resolved) if resolved == *address => Ok(Some(name)),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[test]
+fn test_namehash() {
+    assert_eq!(to_hex(&namehash("")), format!("0x{}", "0".repeat(64)));
+    assert_eq!(
+        to_hex(&namehash("eth")),
+        "0x93cdeb708b7545dc668eb9280176169d1c33cfd8ed6f04690a0bcc88a93fc4ae"
+    );
+    assert_eq!(
+        to_hex(&namehash("foo.eth")),
+        "0xde9b09fd7c5f901e23a3f19fecc54828e9c848539801e86591bd9801b019f84f"
+    );
+
+    let mut data = from_hex(&format!("{:064x}{:064x}", 32, 11)).unwrap();
+    data.extend_from_slice(b"vitalik.eth");
+    data.resize(96, 0);
+    assert_eq!(
+        decode_string(&data).unwrap().as_deref(),
+        Some("vitalik.eth")
+    );
+    assert!(decode_address(&[0u8; 32]).unwrap().is_none());
+    // An offset near usize::MAX is an error, not an overflow.
+    assert!(decode_string(&[0xff; 64]).is_err());
+    let mut data = from_hex(&format!("{:064x}", 32)).unwrap();
+    data.extend_from_slice(&[0xff; 32]);
+    assert!(decode_string(&data).is_err());
+}
+
+#[tokio::test]
+async fn test_resolve_over_rpc() {
+    use axum::{Json, Router, routing::post};
+
+    let vitalik: Address = "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045"
+        .parse()
+        .unwrap();
+    let resolver = "0x231b0Ee14048e9dCcD1d247744d114a4EB5E8E63";
+    let word = |address: &str| format!("0x{:0>64}", address[2..].to_ascii_lowercase());
+    let (vitalik_word, resolver_word) = (word(vitalik.as_str()), word(resolver));
+    let mut name = format!("0x{:064x}{:064x}", 32, 11);
+    name.push_str(&to_hex(b"vitalik.eth")[2..]);
+    name.push_str(&"0".repeat(42));
+
+    // Knows vitalik.eth and its reverse record, nothing else.
+    let node = move |name: &str| to_hex(&namehash(name))[2..].to_string();
+    let rpc = Router::new().route(
+        "/",
+        post(move |Json(request): Json<Value>| async move {
+            let call = &request["params"][0];
+            let data = call["data"].as_str().unwrap();
+            let (selector, node_hex) = (&data[2..10], &data[10..]);
+            let known = node_hex == node("vitalik.eth")
+                || node_hex == node("d8da6bf26964af9d7eed9e03e53415d37aa96045.addr.reverse");
+            let result = match selector {
+                _ if !known => format!("0x{}", "0".repeat(64)),
+                RESOLVER_SELECTOR => resolver_word.clone(),
+                ADDR_SELECTOR => vitalik_word.clone(),
+                NAME_SELECTOR => name.clone(),
+                _ => unreachable!(),
+            };
+            Json(json!({"jsonrpc": "2.0", "id": request["id"], "result": result}))
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, rpc).await });
+
+    let mock = crate::mock_blockscout::MockBlockscout::start()
+        .await
+        .unwrap();
+    let api = mock.api().with_ens_rpc(EnsRpc::new(&url));
+    assert_eq!(
+        api.resolve_name(1, "vitalik.eth").await.unwrap(),
+        Some(vitalik.clone())
+    );
+    assert_eq!(api.resolve_name(1, "nobody.eth").await.unwrap(), None);
+    assert_eq!(
+        api.lookup_address_name(1, &vitalik)
+            .await
+            .unwrap()
+            .as_deref(),
+        Some("vitalik.eth")
+    );
+    let other: Address = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"
+        .parse()
+        .unwrap();
+    assert_eq!(api.lookup_address_name(1, &other).await.unwrap(), None);
+    // Other chains ask their name service, which knows neither.
+    assert_eq!(api.resolve_name(100, "vitalik.eth").await.unwrap(), None);
+    assert_eq!(api.lookup_address_name(100, &vitalik).await.unwrap(), None);
+
+    // A failed lookup is not retried right away.
+    let api = mock.api().with_ens_rpc(EnsRpc::new("http://127.0.0.1:1"));
+    assert!(api.lookup_address_name(1, &vitalik).await.is_err());
+    let e = api.lookup_address_name(1, &vitalik).await.unwrap_err();
+    assert!(e.to_string().contains("failed less than"));
+}
@@ -0,0 +1,220 @@
+use crate::abi;
+use crate::types::keccak256;
+use serde_json::{Value, json};
+use std::sync::LazyLock;
+
+// Events common enough to decode without the emitter's ABI. Blockscout only decodes
+// logs of verified contracts, these fill in `decoded` for the rest. ERC-20 and
+// ERC-721 share `Transfer` and `Approval` topics and are told apart by how many
+// parameters are indexed.
+const SIGNATURES: &[&str] = &[
+    // ERC-20
+    "Transfer(address indexed from, address indexed to, uint256 value)",
+    "Approval(address indexed owner, address indexed spender, uint256 value)",
+    // ERC-721
+    "Transfer(address indexed from, address indexed to, uint256 indexed tokenId)",
+    "Approval(address indexed owner, address indexed approved, uint256 indexed tokenId)",
+    "ApprovalForAll(address indexed owner, address indexed operator, bool approved)",
+    // ERC-1155
+    "TransferSingle(address indexed operator, address indexed from, address indexed to, uint256 id, uint256 value)",
+    "TransferBatch(address indexed operator, address indexed from, address indexed to, uint256[] ids, uint256[] values)",
+    "URI(string value, uint256 indexed id)",
+    // WETH
+    "Deposit(address indexed dst, uint256 wad)",
+    "Withdrawal(address indexed src, uint256 wad)",
+    // Uniswap V2
+    "Swap(address indexed sender, uint256 amount0In, uint256 amount1In, uint256 amount0Out, uint256 amount1Out, address indexed to)",
+    "Sync(uint112 reserve0, uint112 reserve1)",
+    "Mint(address indexed sender, uint256 amount0, uint256 amount1)",
+    "Burn(address indexed sender, uint256 amount0, uint256 amount1, address indexed to)",
+    "PairCreated(address indexed token0, address indexed token1, address pair, uint256 allPairsLength)",
+    // Uniswap V3
+    "Swap(address indexed sender, address indexed recipient, int256 amount0, int256 amount1, uint160 sqrtPriceX96, uint128 liquidity, int24 tick)",
+    "Mint(address sender, address indexed owner, int24 indexed tickLower, int24 indexed tickUpper, uint128 amount, uint256 amount0, uint256 amount1)",
+    "Burn(address indexed owner, int24 indexed tickLower, int24 indexed tickUpper, uint128 amount, uint256 amount0, uint256 amount1)",
+    "Collect(address indexed owner, address recipient, int24 indexed tickLower, int24 indexed tickUpper, uint128 amount0, uint128 amount1)",
+    "PoolCreated(address indexed token0, address indexed token1, uint24 indexed fee, int24 tickSpacing, address pool)",
+    // Ownership, roles, pausing and proxies
+    "OwnershipTransferred(address indexed previousOwner, address indexed newOwner)",
+    "OwnershipTransferStarted(address indexed previousOwner, address indexed newOwner)",
+    "RoleGranted(bytes32 indexed role, address indexed account, address indexed sender)",
+    "RoleRevoked(bytes32 indexed role, address indexed account, address indexed sender)",
+    "Paused(address account)",
+    "Unpaused(address account)",
+    "Upgraded(address indexed implementation)",
+    "AdminChanged(address previousAdmin, address newAdmin)",
+];
+
+#[derive(Debug)]
+struct Param {
+    name: String,
+    typ: String,
+    indexed: bool,
+}
+
+#[derive(Debug)]
+struct Event {
+    // As written above, Blockscout's `method_call`.
+    declaration: &'static str,
+    topic: String,
+    params: Vec<Param>,
+}
+
+impl Event {
+    fn parse(declaration: &'static str) -> Option<Self> {
+        let (name, rest) = declaration.split_once('(')?;
+        let params = rest
+            .strip_suffix(')')?
+            .split(", ")
+            .filter(|param| !param.is_empty())
+            .map(|param| {
+                let words: Vec<&str> = param.split_whitespace().collect();
+                match words.as_slice() {
+                    [typ, "indexed", name] => Some((typ, true, name)),
+                    [typ, name] => Some((typ, false, name)),
+                    _ => None,
+                }
+                .map(|(typ, indexed, name)| Param {
+                    name: name.to_string(),
+                    typ: typ.to_string(),
+                    indexed,
+                })
+            })
+            .collect::<Option<Vec<Param>>>()?;
+        let types: Vec<&str> = params.iter().map(|param| param.typ.as_str()).collect();
+        let signature = format!("{}({})", name, types.join(","));
+        Some(Event {
+            declaration,
+            topic: abi::to_hex(&keccak256(signature.as_bytes())),
+            params,
+        })
+    }
+
+    fn indexed(&self) -> usize {
+        self.params.iter().filter(|param| param.indexed).count()
+    }
+
+    // Indexed values come from the topics, where dynamic ones are only a hash,
+    // the rest from the data in order.
+    fn decode(&self, topics: &[&str], data: &[u8]) -> Option<Value> {
+        let mut topics = topics.iter();
+        let mut at = 0;
+        let parameters = self
+            .params
+            .iter()
+            .map(|param| {
+                let value = if param.indexed {
+                    let topic = topics.next()?;
+                    if abi::is_dynamic(&param.typ) {
+                        json!(topic)
+                    } else {
+                        abi::decode_word(&param.typ, &abi::from_hex(topic)?)?
+                    }
+                } else {
+                    let value = abi::decode_value(&param.typ, data, at)?;
+                    at += 32;
+                    value
+                };
+                Some(json!({
+                    "indexed": param.indexed,
+                    "name": param.name,
+                    "type": param.typ,
+                    "value": value,
+                }))
+            })
+            .collect::<Option<Vec<Value>>>()?;
+        Some(json!({
+            "method_call": self.declaration,
+            "method_id": &self.topic[2..10],
+            "parameters": parameters,
+            "source": "signature library",
+        }))
+    }
+}
+
+static EVENTS: LazyLock<Vec<Event>> =
+    LazyLock::new(|| SIGNATURES.iter().filter_map(|s| Event::parse(s)).collect());
+
+// Decodes a Blockscout log from its topics and data, None for unknown events.
+pub fn decode_log(log: &Value) -> Option<Value> {
+    let topics: Vec<&str> = log["topics"]
+        .as_array()?
+        .iter()
+        .filter_map(Value::as_str)
+        .collect();
+    let (topic, indexed) = topics.split_first()?;
+    let data = abi::from_hex(log["data"].as_str().unwrap_or("0x"))?;
+    EVENTS
+        .iter()
+        .filter(|event| {
+            event.topic == topic.to_ascii_lowercase() && event.indexed() == indexed.len()
+        })
+        .find_map(|event| event.decode(indexed, &data))
+}
+
+// Fills in `decoded` for the logs of a page the explorer could not decode.
+pub fn decode_logs(page: &mut Value) {
+    let Some(items) = page["items"].as_array_mut() else {
+        return;
+    };
+    for log in items {
+        if log["decoded"].is_null()
+            && let Some(decoded) = decode_log(log)
+        {
+            log["decoded"] = decoded;
+        }
+    }
+}
+
+#[test]
+fn test_decode_logs() {
+    assert_eq!(EVENTS.len(), SIGNATURES.len());
+    let transfer = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+    assert!(EVENTS.iter().filter(|e| e.topic == transfer).count() == 2);
+
+    let word = |hex: &str| format!("0x{:0>64}", hex);
+    let vitalik = "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045";
+    let weth = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2";
+    let mut page = json!({"items": [{
+        "topics": [transfer, word(&vitalik[2..]), word(&weth[2..]), null],
+        "data": word("de0b6b3a7640000"),
+        "decoded": null,
+    }, {
+        "topics": [transfer, word(&vitalik[2..]), word(&weth[2..]), word("2a")],
+        "data": "0x",
+        "decoded": null,
+    }, {
+        // Uniswap V2 Sync.
+        "topics": ["0x1c411e9a96e071241c2f21f7726b17ae89e3cab4c78be50e062b03a9fffbbad1"],
+        "data": format!("{}{}", word("64"), &word("c8")[2..]),
+        "decoded": null,
+    }, {
+        "topics": [word("1234")],
+        "data": "0x",
+        "decoded": null,
+    }, {
+        "topics": [transfer],
+        "data": "0x",
+        "decoded": {"method_call": "kept"},
+    }]});
+    decode_logs(&mut page);
+    let logs = &page["items"];
+    assert_eq!(
+        logs[0]["decoded"],
+        json!({
+            "method_call": "Transfer(address indexed from, address indexed to, uint256 value)",
+            "method_id": "ddf252ad",
+            "parameters": [
+                {"indexed": true, "name": "from", "type": "address", "value": vitalik},
+                {"indexed": true, "name": "to", "type": "address", "value": weth},
+                {"indexed": false, "name": "value", "type": "uint256", "value": "1000000000000000000"},
+            ],
+            "source": "signature library",
+        })
+    );
+    assert_eq!(logs[1]["decoded"]["parameters"][2]["name"], "tokenId");
+    assert_eq!(logs[1]["decoded"]["parameters"][2]["value"], "42");
+    assert_eq!(logs[2]["decoded"]["parameters"][1]["value"], "200");
+    assert!(logs[3]["decoded"].is_null());
+    assert_eq!(logs[4]["decoded"]["method_call"], "kept");
+}
@@ -0,0 +1,561 @@
+use crate::block_scout_api::{
+    API, GetAddressTokenTransfersParams, GetAddressTransactionsParams,
+    GetTransactionTokenTransfersParams,
+};
+use crate::types::{Address, TxHash};
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+pub const MAX_HOPS: usize = 5;
+pub const MAX_FAN_OUT: usize = 20;
+pub const MAX_REQUESTS: usize = 100;
+
+const NATIVE_DECIMALS: i32 = 18;
+
+pub struct TraceOptions {
+    pub max_hops: usize,
+    // In USD at the explorer's exchange rate. Above zero, transfers without a price,
+    // no rate or unknown decimals, are skipped as well.
+    pub min_value: f64,
+    pub max_fan_out: usize,
+    pub max_requests: usize,
+    // Start from the transfers of this transaction instead of the latest ones.
+    pub transaction_hash: Option<TxHash>,
+    // Only follow this token, native transfers are skipped.
+    pub token: Option<Address>,
+}
+
+impl Default for TraceOptions {
+    fn default() -> Self {
+        TraceOptions {
+            max_hops: 3,
+            min_value: 0.0,
+            max_fan_out: 5,
+            max_requests: 30,
+            transaction_hash: None,
+            token: None,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct FlowNode {
+    pub address: Address,
+    pub hop: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub is_contract: bool,
+    // Whether its outgoing transfers were fetched.
+    pub expanded: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct FlowEdge {
+    pub from: Address,
+    pub to: Address,
+    pub transaction_hash: String,
+    // Unknown for some pending or indexing transfers, those are never filtered out by age.
+    pub block_number: Option<u64>,
+    pub timestamp: Option<String>,
+    // `native` or the token contract address.
+    pub asset: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount: Option<f64>,
+    // With the explorer's current exchange rate, when it has one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value_usd: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_id: Option<String>,
+    #[serde(skip)]
+    to_party: Party,
+}
+
+#[derive(Serialize, Debug)]
+pub struct FundFlow {
+    pub start: Address,
+    pub nodes: Vec<FlowNode>,
+    pub edges: Vec<FlowEdge>,
+    pub requests_made: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub truncated: Option<String>,
+    pub errors: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default)]
+struct Party {
+    name: Option<String>,
+    is_contract: bool,
+}
+
+fn party(value: &Value) -> Option<(Address, Party)> {
+    let address = value["hash"].as_str()?.parse().ok()?;
+    let name = value["ens_domain_name"]
+        .as_str()
+        .or_else(|| value["name"].as_str())
+        .map(String::from);
+    let is_contract = value["is_contract"].as_bool().unwrap_or_default();
+    Some((address, Party { name, is_contract }))
+}
+
+fn amount(value: &str, decimals: i32) -> Option<f64> {
+    Some(value.parse::<f64>().ok()? / 10f64.powi(decimals))
+}
+
+fn value_usd(amount: Option<f64>, exchange_rate: &Value) -> Option<f64> {
+    let exchange_rate = match exchange_rate {
+        Value::String(rate) => rate.parse().ok(),
+        rate => rate.as_f64(),
+    };
+    amount
+        .zip(exchange_rate)
+        .map(|(amount, rate)| amount * rate)
+}
+
+fn items(page: &Value) -> &[Value] {
+    page["items"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+fn native_edge(item: &Value) -> Option<FlowEdge> {
+    let value = item["value"].as_str().filter(|value| *value != "0")?;
+    // Reverted transactions moved nothing.
+    if item["result"]
+        .as_str()
+        .is_some_and(|result| result != "success")
+    {
+        return None;
+    }
+    let (from, _) = party(&item["from"])?;
+    let (to, to_party) = party(&item["to"])?;
+    let amount = amount(value, NATIVE_DECIMALS);
+    Some(FlowEdge {
+        from,
+        to,
+        transaction_hash: item["hash"].as_str()?.into(),
+        block_number: item["block_number"].as_u64(),
+        timestamp: item["timestamp"].as_str().map(String::from),
+        asset: "native".into(),
+        symbol: None,
+        value: Some(value.into()),
+        amount,
+        value_usd: value_usd(amount, &item["exchange_rate"]),
+        token_id: None,
+        to_party,
+    })
+}
+
+fn token_edge(item: &Value) -> Option<FlowEdge> {
+    let token = &item["token"];
+    let asset = token["address_hash"]
+        .as_str()
+        .or_else(|| token["address"].as_str())?;
+    // Unknown decimals leave the amount unknown, NFTs are counted as they are.
+    let decimals = token["decimals"]
+        .as_str()
+        .and_then(|decimals| decimals.parse().ok())
+        .or_else(|| {
+            matches!(
+                token["type"].as_str(),
+                Some("ERC-721" | "ERC-1155" | "ERC-404")
+            )
+            .then_some(0)
+        });
+    let value = item["total"]["value"].as_str();
+    let amount = value
+        .zip(decimals)
+        .and_then(|(value, decimals)| amount(value, decimals));
+    let (from, _) = party(&item["from"])?;
+    let (to, to_party) = party(&item["to"])?;
+    Some(FlowEdge {
+        from,
+        to,
+        transaction_hash: item["transaction_hash"].as_str()?.into(),
+        block_number: item["block_number"].as_u64(),
+        timestamp: item["timestamp"].as_str().map(String::from),
+        asset: asset.into(),
+        symbol: token["symbol"].as_str().map(String::from),
+        value: value.map(String::from),
+        amount,
+        value_usd: value_usd(amount, &token["exchange_rate"]),
+        token_id: item["total"]["token_id"].as_str().map(String::from),
+        to_party,
+    })
+}
+
+// Priced transfers by USD value first. Amounts of different assets can't be compared,
+// a spam token easily mints more units than anyone holds ether, so the rest take turns:
+// the largest of every asset, native first, then the second largest and so on.
+fn rank(edges: &mut Vec<FlowEdge>) {
+    let (mut ranked, mut unpriced): (Vec<_>, Vec<_>) = std::mem::take(edges)
+        .into_iter()
+        .partition(|edge| edge.value_usd.is_some());
+    ranked.sort_by(|a, b| {
+        b.value_usd
+            .unwrap_or(0.0)
+            .total_cmp(&a.value_usd.unwrap_or(0.0))
+    });
+    unpriced.sort_by(|a, b| {
+        a.asset
+            .cmp(&b.asset)
+            .then(b.amount.unwrap_or(0.0).total_cmp(&a.amount.unwrap_or(0.0)))
+    });
+    let mut turns: Vec<(usize, bool, FlowEdge)> = Vec::with_capacity(unpriced.len());
+    for edge in unpriced {
+        let turn = match turns.last() {
+            Some((turn, _, last)) if last.asset == edge.asset => turn + 1,
+            _ => 0,
+        };
+        turns.push((turn, edge.asset != "native", edge));
+    }
+    // Stable, so assets keep their order within a turn.
+    turns.sort_by_key(|(turn, is_token, _)| (*turn, *is_token));
+    ranked.extend(turns.into_iter().map(|(_, _, edge)| edge));
+    *edges = ranked;
+}
+
+// Amounts of different assets can't be held to one threshold, so only priced transfers
+// can pass a minimum.
+fn worth_following(edge: &FlowEdge, min_value: f64) -> bool {
+    min_value <= 0.0 || edge.value_usd.is_some_and(|value| value >= min_value)
+}
+
+struct Tracer<'a> {
+    api: &'a API,
+    chain_id: i32,
+    options: &'a TraceOptions,
+    requests_made: usize,
+}
+
+impl Tracer<'_> {
+    fn cost(&self) -> usize {
+        if self.options.token.is_some() { 1 } else { 2 }
+    }
+
+    fn token_params(&self) -> String {
+        self.options
+            .token
+            .as_ref()
+            .map(|token| token.to_string())
+            .unwrap_or_default()
+    }
+
+    // Outgoing transfers from the address, latest first page only.
+    async fn address_transfers(&mut self, address: &Address) -> Result<Vec<FlowEdge>> {
+        self.requests_made += self.cost();
+        let native = async {
+            if self.options.token.is_some() {
+                return Ok(Value::Null);
+            }
+            let params = GetAddressTransactionsParams {
+                filter: "from".into(),
+            };
+            self.api
+                .get_address_transactions(self.chain_id, address.clone(), params)
+                .await
+        };
+        let params = GetAddressTokenTransfersParams {
+            filter: "from".into(),
+            token: self.token_params(),
+            ..Default::default()
+        };
+        let tokens = self
+            .api
+            .get_address_token_transfers(self.chain_id, address.clone(), params);
+        let (native, tokens) = tokio::join!(native, tokens);
+        let mut edges: Vec<FlowEdge> = items(&native?).iter().filter_map(native_edge).collect();
+        edges.extend(items(&tokens?).iter().filter_map(token_edge));
+        Ok(edges)
+    }
+
+    async fn transaction_transfers(&mut self, hash: &TxHash) -> Result<Vec<FlowEdge>> {
+        self.requests_made += self.cost();
+        let native = async {
+            if self.options.token.is_some() {
+                return Ok(None);
+            }
+            self.api
+                .get_transaction_info(self.chain_id, hash.clone())
+                .await
+                .map(|info| native_edge(&info))
+        };
+        let tokens = self.api.get_transaction_token_transfers(
+            self.chain_id,
+            hash.clone(),
+            GetTransactionTokenTransfersParams::default(),
+        );
+        let (native, tokens) = tokio::join!(native, tokens);
+        let mut edges: Vec<FlowEdge> = native?.into_iter().collect();
+        edges.extend(items(&tokens?).iter().filter_map(token_edge));
+        Ok(edges)
+    }
+}
+
+// Follows outgoing native and token transfers breadth first from `start`. Funds can
+// only leave an address after they arrived, so later hops skip transfers older than
+// the one that led there. Contracts are not expanded, their transfers are mostly
+// other people's money. Fan-out keeps the largest transfers, see `rank`.
+pub async fn trace(api: &API, chain_id: i32, start: Address, options: &TraceOptions) -> FundFlow {
+    let mut tracer = Tracer {
+        api,
+        chain_id,
+        options,
+        requests_made: 0,
+    };
+    let mut flow = FundFlow {
+        start: start.clone(),
+        nodes: vec![FlowNode {
+            address: start.clone(),
+            hop: 0,
+            name: None,
+            is_contract: false,
+            expanded: false,
+        }],
+        edges: Vec::new(),
+        requests_made: 0,
+        truncated: None,
+        errors: BTreeMap::new(),
+    };
+    let mut index = HashMap::from([(start.clone(), 0)]);
+    let mut frontier: Vec<(Address, Option<u64>)> = vec![(start, None)];
+
+    'hops: for hop in 0..options.max_hops {
+        let mut next = Vec::new();
+        for (address, since) in frontier {
+            if tracer.requests_made + tracer.cost() > options.max_requests {
+                flow.truncated = Some(format!(
+                    "request budget of {} exhausted",
+                    options.max_requests
+                ));
+                break 'hops;
+            }
+            let transfers = match (&options.transaction_hash, hop) {
+                (Some(hash), 0) => tracer.transaction_transfers(hash).await,
+                _ => tracer.address_transfers(&address).await,
+            };
+            let mut edges: Vec<FlowEdge> = match transfers {
+                Ok(edges) => edges
+                    .into_iter()
+                    .filter(|edge| edge.from == address && edge.to != address)
+                    .filter(|edge| {
+                        options
+                            .token
+                            .as_ref()
+                            .is_none_or(|token| edge.asset.eq_ignore_ascii_case(token.as_str()))
+                    })
+                    .filter(|edge| {
+                        since.is_none_or(|since| {
+                            edge.block_number.is_none_or(|block| block >= since)
+                        })
+                    })
+                    .filter(|edge| worth_following(edge, options.min_value))
+                    .collect(),
+                Err(e) => {
+                    flow.errors.insert(address.to_string(), e.to_string());
+                    continue;
+                }
+            };
+            flow.nodes[index[&address]].expanded = true;
+            rank(&mut edges);
+            edges.truncate(options.max_fan_out);
+            for mut edge in edges {
+                if !index.contains_key(&edge.to) {
+                    let party = std::mem::take(&mut edge.to_party);
+                    if !party.is_contract {
+                        next.push((edge.to.clone(), edge.block_number.or(since)));
+                    }
+                    index.insert(edge.to.clone(), flow.nodes.len());
+                    flow.nodes.push(FlowNode {
+                        address: edge.to.clone(),
+                        hop: hop + 1,
+                        name: party.name,
+                        is_contract: party.is_contract,
+                        expanded: false,
+                    });
+                }
+                flow.edges.push(edge);
+            }
+        }
+        if next.is_empty() {
+            break;
+        }
+        frontier = next;
+    }
+    flow.requests_made = tracer.requests_made;
+    flow
+}
+
+#[tokio::test]
+async fn test_trace() {
+    let mock = crate::mock_blockscout::MockBlockscout::start()
+        .await
+        .unwrap();
+    let api = mock.api();
+    let address = |digit: char| -> Address {
+        format!("0x{}", digit.to_string().repeat(40))
+            .parse()
+            .unwrap()
+    };
+    let pairs = |flow: &FundFlow| -> Vec<(String, String, String)> {
+        flow.edges
+            .iter()
+            .map(|edge| {
+                (
+                    edge.from.to_string()[2..3].into(),
+                    edge.to.to_string()[2..3].into(),
+                    edge.asset.clone(),
+                )
+            })
+            .collect()
+    };
+    let options = TraceOptions {
+        min_value: 10.0,
+        ..Default::default()
+    };
+
+    // 1 sends ether to 2 and dust to 3, USDC to 4, 2 passes the ether on to 5. Ether
+    // is at 100 USD, the dust is worth 1 USD.
+    let flow = trace(&api, 1, address('1'), &options).await;
+    let usdc = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48".to_string();
+    assert_eq!(
+        pairs(&flow),
+        vec![
+            ("1".into(), "4".into(), usdc.clone()),
+            ("1".into(), "2".into(), "native".into()),
+            ("2".into(), "5".into(), "native".into()),
+        ]
+    );
+    assert_eq!(flow.edges[0].amount, Some(1000.0));
+    let graph = crate::render::Graph::from_fund_flow(&flow);
+    assert_eq!(graph.edges[0].label, "1000 USDC");
+    assert_eq!(graph.edges[1].label, "5 native");
+    assert_eq!(flow.edges[2].amount, Some(4.5));
+    let hops: Vec<usize> = flow.nodes.iter().map(|node| node.hop).collect();
+    assert_eq!(hops, vec![0, 1, 1, 2]);
+    assert_eq!(flow.requests_made, 8);
+    assert!(flow.truncated.is_none());
+
+    let flow = trace(
+        &api,
+        1,
+        address('1'),
+        &TraceOptions {
+            max_requests: 3,
+            max_fan_out: 1,
+            ..Default::default()
+        },
+    )
+    .await;
+    assert_eq!(pairs(&flow), vec![("1".into(), "4".into(), usdc.clone())]);
+    assert_eq!(
+        flow.truncated.as_deref(),
+        Some("request budget of 3 exhausted")
+    );
+    assert!(!flow.nodes[1].expanded);
+
+    let flow = trace(
+        &api,
+        1,
+        address('1'),
+        &TraceOptions {
+            token: Some(usdc.parse().unwrap()),
+            ..Default::default()
+        },
+    )
+    .await;
+    assert_eq!(pairs(&flow), vec![("1".into(), "4".into(), usdc)]);
+
+    // Starting from a transaction only its transfers count, WETH is a contract.
+    let vitalik: Address = "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045"
+        .parse()
+        .unwrap();
+    let flow = trace(
+        &api,
+        1,
+        vitalik,
+        &TraceOptions {
+            transaction_hash: Some(
+                "0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060"
+                    .parse()
+                    .unwrap(),
+            ),
+            ..Default::default()
+        },
+    )
+    .await;
+    assert_eq!(flow.edges.len(), 1);
+    assert_eq!(flow.nodes[1].name.as_deref(), Some("WETH9"));
+    assert!(flow.nodes[1].is_contract && !flow.nodes[1].expanded);
+    assert_eq!(flow.requests_made, 2);
+    assert!(
+        mock.requests()
+            .contains(&format!("1:addresses/{}/token-transfers?filter=from&token=0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48", address('1')))
+    );
+}
+
+#[test]
+fn test_rank() {
+    let party =
+        |digit: char| serde_json::json!({ "hash": format!("0x{}", digit.to_string().repeat(40)) });
+    let native = |to: char, value: &str, exchange_rate: Option<&str>| {
+        native_edge(&serde_json::json!({
+            "hash": "0x01",
+            "from": party('1'),
+            "to": party(to),
+            "value": value,
+            "exchange_rate": exchange_rate,
+        }))
+        .unwrap()
+    };
+    let token = |to: char, symbol: &str, value: &str, exchange_rate: Option<&str>| {
+        token_edge(&serde_json::json!({
+            "transaction_hash": "0x02",
+            "from": party('1'),
+            "to": party(to),
+            "token": {
+                "address_hash": format!("0x{}", symbol),
+                "symbol": symbol,
+                "decimals": "6",
+                "exchange_rate": exchange_rate,
+            },
+            "total": { "value": value },
+        }))
+        .unwrap()
+    };
+    let mut edges = vec![
+        token('2', "SPAM", "1000000000000000000", None),
+        token('3', "SPAM", "5000000000000000000", None),
+        native('4', "1000000000000000000", None),
+        token('5', "USDC", "100000000", Some("1.0")),
+        token('6', "JUNK", "1000000", None),
+        native('7', "1000000000000000000", Some("3000")),
+    ];
+    assert_eq!(edges[5].value_usd, Some(3000.0));
+    rank(&mut edges);
+    let order: String = edges
+        .iter()
+        .map(|edge| edge.to.to_string().chars().nth(2).unwrap())
+        .collect();
+    assert_eq!(order, "754632");
+
+    // A minimum only lets priced transfers through, huge unpriced amounts included.
+    assert!(worth_following(&edges[1], 100.0) && !worth_following(&edges[1], 101.0));
+    assert!(!worth_following(&edges[5], 1.0) && worth_following(&edges[5], 0.0));
+    let spam = token_edge(&serde_json::json!({
+        "transaction_hash": "0x03",
+        "from": party('1'),
+        "to": party('8'),
+        "token": {"address_hash": "0xSPAM", "decimals": null, "type": "ERC-20"},
+        "total": {"value": "1000000000000000000000000"},
+    }))
+    .unwrap();
+    assert_eq!(spam.amount, None);
+    assert!(!worth_following(&spam, 1.0));
+}
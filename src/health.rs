@@ -0,0 +1,154 @@
+use crate::block_scout_api::API;
+use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tokio::task::JoinSet;
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+// How long a readiness result is served before the upstreams are probed again.
+const READINESS_TTL: Duration = Duration::from_secs(5);
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ProbeStatus {
+    pub ok: bool,
+    pub latency_ms: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Readiness {
+    pub ready: bool,
+    pub chains_registry: ProbeStatus,
+    pub chains: BTreeMap<i32, ProbeStatus>,
+}
+
+async fn probe<F>(future: F) -> ProbeStatus
+where
+    F: Future<Output = anyhow::Result<()>>,
+{
+    let start = Instant::now();
+    let error = match tokio::time::timeout(PROBE_TIMEOUT, future).await {
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => Some(e.to_string()),
+        Err(_) => Some(format!("timed out after {:?}", PROBE_TIMEOUT)),
+    };
+    ProbeStatus {
+        ok: error.is_none(),
+        latency_ms: start.elapsed().as_millis(),
+        error,
+    }
+}
+
+// Probes the registry and the chains the operator configured, built-in explorers are
+// left out so readiness does not hang on third parties nobody asked for.
+pub async fn check_readiness(api: &API) -> Readiness {
+    let mut probes = JoinSet::new();
+    let mut probed = HashMap::new();
+    for chain_id in api.configured_chain_ids() {
+        let api = api.clone();
+        let handle = probes
+            .spawn(async move { probe(async { api.get_stats(chain_id).await.map(|_| ()) }).await });
+        probed.insert(handle.id(), chain_id);
+    }
+    let chains_registry = probe(api.ping_chains_registry()).await;
+
+    let mut chains = BTreeMap::new();
+    while let Some(res) = probes.join_next_with_id().await {
+        let (id, status) = match res {
+            Ok((id, status)) => (id, status),
+            Err(e) => (
+                e.id(),
+                ProbeStatus {
+                    ok: false,
+                    latency_ms: 0,
+                    error: Some(format!("probe failed: {}", e)),
+                },
+            ),
+        };
+        chains.insert(probed[&id], status);
+    }
+    Readiness {
+        ready: chains_registry.ok && chains.values().all(|status| status.ok),
+        chains_registry,
+        chains,
+    }
+}
+
+pub async fn healthz() -> &'static str {
+    "ok"
+}
+
+// Caches the last readiness result, so frequent probes do not fan out upstream. The
+// lock is held while checking, concurrent requests wait for the same result.
+#[derive(Clone)]
+pub struct ReadinessCheck {
+    api: API,
+    last: Arc<Mutex<Option<(Instant, Readiness)>>>,
+}
+
+impl ReadinessCheck {
+    pub fn new(api: API) -> Self {
+        ReadinessCheck {
+            api,
+            last: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub async fn check(&self) -> Readiness {
+        let mut last = self.last.lock().await;
+        if let Some((checked_at, readiness)) = last.as_ref()
+            && checked_at.elapsed() < READINESS_TTL
+        {
+            return readiness.clone();
+        }
+        let readiness = check_readiness(&self.api).await;
+        *last = Some((Instant::now(), readiness.clone()));
+        readiness
+    }
+}
+
+pub async fn readyz(State(check): State<ReadinessCheck>) -> impl IntoResponse {
+    let readiness = check.check().await;
+    let status = if readiness.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(readiness))
+}
+
+#[tokio::test]
+async fn test_readiness() {
+    use crate::block_scout_api::ExplorerConfig;
+
+    let mock = crate::mock_blockscout::MockBlockscout::start()
+        .await
+        .unwrap();
+    let explorer = |url: String| ExplorerConfig {
+        url: Some(url),
+        ..Default::default()
+    };
+    let api = API::with_explorers(HashMap::from([
+        (1, explorer(mock.explorer_url(1))),
+        (2, explorer("http://127.0.0.1:1/".into())),
+    ]))
+    .with_chains_registry(&mock.registry_url());
+    let check = ReadinessCheck::new(api);
+
+    let readiness = check.check().await;
+    assert!(!readiness.ready);
+    assert!(readiness.chains_registry.ok);
+    assert_eq!(readiness.chains.keys().copied().collect::<Vec<_>>(), [1, 2]);
+    assert!(readiness.chains[&1].ok);
+    assert!(!readiness.chains[&2].ok);
+    assert_eq!(mock.requests(), vec!["1:stats"]);
+
+    // Answered from the cache.
+    check.check().await;
+    assert_eq!(mock.requests(), vec!["1:stats"]);
+}
@@ -1,2 +1,25 @@
+pub mod abi;
+pub mod approvals;
+pub mod auth;
 pub mod block_scout_api;
-pub mod tools;
\ No newline at end of file
+pub mod call_tree;
+pub mod cassette;
+pub mod completions;
+pub mod ens;
+pub mod events;
+pub mod fund_flow;
+pub mod health;
+pub mod metrics;
+#[cfg(any(test, feature = "test-util"))]
+pub mod mock_blockscout;
+pub mod multichain;
+pub mod oauth;
+pub mod origin;
+pub mod portfolio;
+pub mod prompts;
+pub mod render;
+pub mod sessions;
+pub mod shutdown;
+pub mod telemetry;
+pub mod tools;
+pub mod types;
@@ -0,0 +1,169 @@
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+use std::sync::LazyLock;
+use std::time::Duration;
+
+const LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+pub struct Metrics {
+    registry: Registry,
+    tool_calls: IntCounterVec,
+    tool_call_duration: HistogramVec,
+    upstream_requests: IntCounterVec,
+    upstream_request_duration: HistogramVec,
+    cache_requests: IntCounterVec,
+    active_sessions: IntGauge,
+}
+
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("blocks_mcp".into()), None).unwrap();
+        let tool_calls = IntCounterVec::new(
+            Opts::new("tool_calls_total", "MCP tool calls by tool and outcome"),
+            &["tool", "status"],
+        )
+        .unwrap();
+        let tool_call_duration = HistogramVec::new(
+            HistogramOpts::new("tool_call_duration_seconds", "MCP tool call latency")
+                .buckets(LATENCY_BUCKETS.to_vec()),
+            &["tool"],
+        )
+        .unwrap();
+        let upstream_requests = IntCounterVec::new(
+            Opts::new(
+                "upstream_requests_total",
+                "Blockscout requests by chain, endpoint and http status",
+            ),
+            &["chain_id", "endpoint", "status"],
+        )
+        .unwrap();
+        let upstream_request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "upstream_request_duration_seconds",
+                "Blockscout request latency",
+            )
+            .buckets(LATENCY_BUCKETS.to_vec()),
+            &["chain_id", "endpoint"],
+        )
+        .unwrap();
+        let cache_requests = IntCounterVec::new(
+            Opts::new("cache_requests_total", "Cache lookups by cache and result"),
+            &["cache", "result"],
+        )
+        .unwrap();
+        let active_sessions =
+            IntGauge::new("active_sessions", "Live MCP streamable http sessions").unwrap();
+
+        registry.register(Box::new(tool_calls.clone())).unwrap();
+        registry
+            .register(Box::new(tool_call_duration.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(upstream_requests.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(upstream_request_duration.clone()))
+            .unwrap();
+        registry.register(Box::new(cache_requests.clone())).unwrap();
+        registry
+            .register(Box::new(active_sessions.clone()))
+            .unwrap();
+
+        Metrics {
+            registry,
+            tool_calls,
+            tool_call_duration,
+            upstream_requests,
+            upstream_request_duration,
+            cache_requests,
+            active_sessions,
+        }
+    }
+
+    pub fn observe_tool_call(&self, tool: &str, success: bool, elapsed: Duration) {
+        let status = if success { "ok" } else { "error" };
+        self.tool_calls.with_label_values(&[tool, status]).inc();
+        self.tool_call_duration
+            .with_label_values(&[tool])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    // `status` is the http status code, or "error" when no response was received.
+    pub fn observe_upstream_request(
+        &self,
+        chain_id: i32,
+        path: &str,
+        status: &str,
+        elapsed: Duration,
+    ) {
+        let chain_id = chain_id.to_string();
+        let endpoint = endpoint_label(path);
+        self.upstream_requests
+            .with_label_values(&[&chain_id, &endpoint, status])
+            .inc();
+        self.upstream_request_duration
+            .with_label_values(&[&chain_id, &endpoint])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn observe_cache(&self, cache: &str, hit: bool) {
+        let result = if hit { "hit" } else { "miss" };
+        self.cache_requests
+            .with_label_values(&[cache, result])
+            .inc();
+    }
+
+    pub fn set_active_sessions(&self, sessions: usize) {
+        self.active_sessions.set(sessions as i64);
+    }
+
+    pub fn encode(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+}
+
+// Replaces hashes and numbers in the path so every address or block
+// does not become its own time series.
+pub fn endpoint_label(path: &str) -> String {
+    path.split('/')
+        .map(|segment| {
+            if segment.starts_with("0x") {
+                "{hash}"
+            } else if !segment.is_empty() && segment.chars().all(|c| c.is_ascii_digit()) {
+                "{id}"
+            } else {
+                segment
+            }
+        })
+        .collect::<Vec<&str>>()
+        .join("/")
+}
+
+#[test]
+fn test_endpoint_label() {
+    assert_eq!(endpoint_label("stats"), "stats");
+    assert_eq!(
+        endpoint_label("addresses/0xabc/token-transfers"),
+        "addresses/{hash}/token-transfers"
+    );
+    assert_eq!(
+        endpoint_label("tokens/0xabc/instances/42/holders"),
+        "tokens/{hash}/instances/{id}/holders"
+    );
+
+    METRICS.observe_tool_call("test_endpoint_label", true, Duration::from_millis(5));
+    assert!(
+        METRICS
+            .encode()
+            .contains("blocks_mcp_tool_calls_total{status=\"ok\",tool=\"test_endpoint_label\"} 1")
+    );
+}
@@ -0,0 +1,304 @@
+use crate::block_scout_api::{API, ExplorerConfig, MERLIN_CHAIN_ID};
+use crate::metrics::endpoint_label;
+use anyhow::Result;
+use axum::{
+    Json, Router,
+    extract::{OriginalUri, Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::task::JoinHandle;
+
+pub const FIXTURES_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/blockscout");
+
+// A local stand-in for the chain registry and the explorers' `/api/v2` routes, serving
+// json fixtures so tests run offline and give the same answers every time.
+//
+// `/api/v2/<path>` is answered from the first fixture found among
+// `chains/<chain_id>/api/v2/<path>.json`, `api/v2/<path>.json` and the same two with
+// hashes and numbers replaced like the metrics endpoint label, e.g.
+// `api/v2/addresses/{hash}/tokens.json`. The registry is served from `chains.json`
+// with every explorer pointed back at this server, the name service under `/names`
+// from `names/api/v1/<chain_id>/...`. A fixture holding Blockscout's not found body,
+// `{"message": "Not found"}`, is answered with a 404 like the real thing.
+//
+// The Etherscan compatible `/api?module=<module>&action=<action>` is answered from
+// `api/<module>/<action>.json`, looked up the same way. `getLogs` filters the logs of
+// the fixture by block range and topic and pages them with `page` and `offset`.
+pub struct MockBlockscout {
+    url: String,
+    requests: Arc<Mutex<Vec<String>>>,
+    server: JoinHandle<()>,
+}
+
+#[derive(Clone)]
+struct MockState {
+    url: String,
+    fixtures: Arc<PathBuf>,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl Drop for MockBlockscout {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+impl MockBlockscout {
+    pub async fn start() -> Result<Self> {
+        Self::with_fixtures(FIXTURES_DIR).await
+    }
+
+    pub async fn with_fixtures(fixtures: impl Into<PathBuf>) -> Result<Self> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let router = Router::new()
+            .route("/api/chains", get(chains))
+            .route("/api/chains/{chain_id}", get(chain))
+            .route("/chain/{chain_id}/api", get(rpc))
+            .route("/chain/{chain_id}/api/v2/{*path}", get(explorer))
+            .route("/names/{*path}", get(names))
+            .with_state(MockState {
+                url: url.clone(),
+                fixtures: Arc::new(fixtures.into()),
+                requests: requests.clone(),
+            });
+        let server = tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, router).await {
+                tracing::error!("mock blockscout stopped: {}", e);
+            }
+        });
+        Ok(MockBlockscout {
+            url,
+            requests,
+            server,
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn registry_url(&self) -> String {
+        format!("{}/api/chains", self.url)
+    }
+
+    pub fn name_service_url(&self) -> String {
+        format!("{}/names", self.url)
+    }
+
+    pub fn explorer_url(&self, chain_id: i32) -> String {
+        explorer_url(&self.url, chain_id)
+    }
+
+    // An API talking only to this server, Merlin included.
+    pub fn api(&self) -> API {
+        self.api_with_explorers(HashMap::new())
+    }
+
+    pub fn api_with_explorers(&self, mut explorers: HashMap<i32, ExplorerConfig>) -> API {
+        for (chain_id, explorer) in explorers.iter_mut() {
+            explorer
+                .url
+                .get_or_insert_with(|| self.explorer_url(*chain_id));
+        }
+        explorers
+            .entry(MERLIN_CHAIN_ID)
+            .or_default()
+            .url
+            .get_or_insert_with(|| self.explorer_url(MERLIN_CHAIN_ID));
+        API::with_explorers(explorers)
+            .with_chains_registry(&self.registry_url())
+            .with_name_service(&self.name_service_url())
+    }
+
+    // Explorer requests received so far, as `<chain_id>:<path>?<query>`.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn explorer_url(url: &str, chain_id: i32) -> String {
+    format!("{}/chain/{}/", url, chain_id)
+}
+
+fn not_found() -> Response {
+    (StatusCode::NOT_FOUND, Json(json!({"message": "Not found"}))).into_response()
+}
+
+async fn read_fixture(path: PathBuf) -> Option<Value> {
+    let content = tokio::fs::read_to_string(path).await.ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+async fn read_chains(state: &MockState) -> Option<serde_json::Map<String, Value>> {
+    let mut chains = match read_fixture(state.fixtures.join("chains.json")).await? {
+        Value::Object(chains) => chains,
+        _ => return None,
+    };
+    for (chain_id, chain) in chains.iter_mut() {
+        let url = explorer_url(&state.url, chain_id.parse().ok()?);
+        chain["explorers"] = json!([{"url": url, "hostedBy": "blockscout"}]);
+    }
+    Some(chains)
+}
+
+async fn chains(State(state): State<MockState>) -> Response {
+    match read_chains(&state).await {
+        Some(chains) => Json(chains).into_response(),
+        None => not_found(),
+    }
+}
+
+async fn chain(State(state): State<MockState>, Path(chain_id): Path<String>) -> Response {
+    match read_chains(&state)
+        .await
+        .and_then(|mut chains| chains.remove(&chain_id))
+    {
+        Some(chain) => Json(chain).into_response(),
+        None => not_found(),
+    }
+}
+
+async fn names(State(state): State<MockState>, Path(path): Path<String>) -> Response {
+    if path
+        .split('/')
+        .any(|segment| segment == ".." || segment.is_empty())
+    {
+        return not_found();
+    }
+    let fixture = state.fixtures.join(format!("names/{}.json", path));
+    match read_fixture(fixture).await {
+        Some(fixture) => Json(fixture).into_response(),
+        None => not_found(),
+    }
+}
+
+async fn explorer(
+    State(state): State<MockState>,
+    Path((chain_id, path)): Path<(i32, String)>,
+    OriginalUri(uri): OriginalUri,
+) -> Response {
+    let query = uri.query().map(|q| format!("?{}", q)).unwrap_or_default();
+    state
+        .requests
+        .lock()
+        .unwrap()
+        .push(format!("{}:{}{}", chain_id, path, query));
+
+    if path
+        .split('/')
+        .any(|segment| segment == ".." || segment.is_empty())
+    {
+        return not_found();
+    }
+    let chain_dir = state.fixtures.join(format!("chains/{}", chain_id));
+    // Later pages, asked for with the `next_page_params` of the previous one, are
+    // `<path>.page-<items_count>.json`.
+    let page = uri
+        .query()
+        .and_then(|query| {
+            query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("items_count="))
+        })
+        .map(|items_count| format!(".page-{}", items_count))
+        .unwrap_or_default();
+    for relative in [path.clone(), endpoint_label(&path)] {
+        let relative = format!("{}{}", relative, page);
+        let relative = format!("api/v2/{}.json", relative);
+        for dir in [&chain_dir, &*state.fixtures] {
+            if let Some(fixture) = read_fixture(dir.join(&relative)).await {
+                if fixture == json!({"message": "Not found"}) {
+                    return not_found();
+                }
+                return Json(fixture).into_response();
+            }
+        }
+    }
+    not_found()
+}
+
+fn get_logs(mut fixture: Value, query: &HashMap<String, String>) -> Value {
+    let logs = fixture["result"].take();
+    let block = |name: &str| query.get(name).and_then(|block| block.parse::<u64>().ok());
+    let (from_block, to_block) = (block("fromBlock"), block("toBlock"));
+    let matches = |log: &Value| {
+        let number = log["blockNumber"]
+            .as_str()
+            .and_then(|number| u64::from_str_radix(number.trim_start_matches("0x"), 16).ok())
+            .unwrap_or_default();
+        from_block.is_none_or(|from| number >= from)
+            && to_block.is_none_or(|to| number <= to)
+            && (0..4).all(|i| {
+                query.get(&format!("topic{}", i)).is_none_or(|topic| {
+                    log["topics"][i]
+                        .as_str()
+                        .is_some_and(|t| t.eq_ignore_ascii_case(topic))
+                })
+            })
+    };
+    let number = |name: &str, default: usize| {
+        query
+            .get(name)
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(default)
+    };
+    let offset = number("offset", 1000);
+    let logs: Vec<&Value> = logs
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .filter(|log| matches(log))
+        .skip(number("page", 1).saturating_sub(1) * offset)
+        .take(offset)
+        .collect();
+    if logs.is_empty() {
+        json!({"message": "No logs found", "result": [], "status": "0"})
+    } else {
+        json!({"message": "OK", "result": logs, "status": "1"})
+    }
+}
+
+async fn rpc(
+    State(state): State<MockState>,
+    Path(chain_id): Path<i32>,
+    Query(query): Query<HashMap<String, String>>,
+    OriginalUri(uri): OriginalUri,
+) -> Response {
+    state.requests.lock().unwrap().push(format!(
+        "{}:api?{}",
+        chain_id,
+        uri.query().unwrap_or_default()
+    ));
+
+    let (Some(module), Some(action)) = (query.get("module"), query.get("action")) else {
+        return not_found();
+    };
+    if ![module, action]
+        .iter()
+        .all(|name| name.chars().all(|c| c.is_ascii_alphanumeric()))
+    {
+        return not_found();
+    }
+    let relative = format!("api/{}/{}.json", module, action);
+    for dir in [
+        state.fixtures.join(format!("chains/{}", chain_id)),
+        state.fixtures.to_path_buf(),
+    ] {
+        if let Some(fixture) = read_fixture(dir.join(&relative)).await {
+            return match (module.as_str(), action.as_str()) {
+                ("logs", "getLogs") => Json(get_logs(fixture, &query)).into_response(),
+                _ => Json(fixture).into_response(),
+            };
+        }
+    }
+    Json(json!({"message": "Unknown action", "result": null, "status": "0"})).into_response()
+}
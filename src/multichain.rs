@@ -0,0 +1,194 @@
+use crate::block_scout_api::{API, StatusError};
+use crate::types::Address;
+use futures::StreamExt;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tokio::time::Instant;
+
+// The most chains a caller may name, two requests go out per chain.
+pub const MAX_CHAINS: usize = 50;
+
+const MAX_CONCURRENT_CHAINS: usize = 8;
+const CHAIN_TIMEOUT: Duration = Duration::from_secs(10);
+// Bounds the whole lookup, chains still waiting for their turn at the deadline fail.
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Serialize, Debug)]
+pub struct ChainActivity {
+    pub chain_id: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain_name: Option<String>,
+    pub is_contract: bool,
+    pub coin_balance: Option<String>,
+    pub transactions_count: Option<String>,
+    pub token_transfers_count: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct AddressActivity {
+    pub address: Address,
+    pub chains_queried: usize,
+    // Most transactions first.
+    pub active: Vec<ChainActivity>,
+    pub inactive: Vec<i32>,
+    pub errors: BTreeMap<i32, String>,
+}
+
+enum ChainResult {
+    Active(ChainActivity),
+    Inactive,
+    Failed(String),
+}
+
+// Every chain in the registry plus the configured ones, mainnets only unless asked.
+// A registry outage leaves just the configured chains.
+pub async fn candidate_chains(api: &API, include_testnets: bool) -> Vec<i32> {
+    let mut chain_ids = api.explorer_chain_ids();
+    match api.get_chains().await {
+        Ok(chains) => chain_ids.extend(
+            chains
+                .iter()
+                .filter(|(_, chain)| include_testnets || !chain.is_testnet())
+                .map(|(chain_id, _)| *chain_id),
+        ),
+        Err(e) => tracing::warn!(
+            "chains registry unavailable, using configured chains: {}",
+            e
+        ),
+    }
+    chain_ids.sort();
+    chain_ids.dedup();
+    chain_ids
+}
+
+fn string_field(data: &Value, field: &str) -> Option<String> {
+    match data.get(field)? {
+        Value::String(value) => Some(value.clone()),
+        Value::Number(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+fn is_zero(value: &Option<String>) -> bool {
+    value.as_deref().is_none_or(|value| value == "0")
+}
+
+async fn chain_activity(api: &API, chain_id: i32, address: &Address) -> ChainResult {
+    let lookup = async {
+        tokio::join!(
+            api.get_address_info(chain_id, address.clone()),
+            api.get_address_counters(chain_id, address.clone())
+        )
+    };
+    let (info, counters) = match tokio::time::timeout(CHAIN_TIMEOUT, lookup).await {
+        Ok(results) => results,
+        Err(_) => return ChainResult::Failed(format!("timed out after {:?}", CHAIN_TIMEOUT)),
+    };
+    // Explorers answer 404 for addresses they have never seen.
+    let info = match info {
+        Ok(info) => info,
+        Err(e) if StatusError::is_not_found(&e) => return ChainResult::Inactive,
+        Err(e) => return ChainResult::Failed(e.to_string()),
+    };
+    let counters = match counters {
+        Ok(counters) => counters,
+        Err(e) if StatusError::is_not_found(&e) => Value::Null,
+        Err(e) => return ChainResult::Failed(e.to_string()),
+    };
+    let activity = ChainActivity {
+        chain_id,
+        chain_name: None,
+        is_contract: info["is_contract"].as_bool().unwrap_or_default(),
+        coin_balance: string_field(&info, "coin_balance"),
+        transactions_count: string_field(&counters, "transactions_count"),
+        token_transfers_count: string_field(&counters, "token_transfers_count"),
+    };
+    if is_zero(&activity.coin_balance)
+        && is_zero(&activity.transactions_count)
+        && is_zero(&activity.token_transfers_count)
+        && !activity.is_contract
+    {
+        return ChainResult::Inactive;
+    }
+    ChainResult::Active(activity)
+}
+
+// Looks the address up on every chain at once, a failing chain only shows up in `errors`.
+pub async fn address_activity(
+    api: &API,
+    address: &Address,
+    chain_ids: Vec<i32>,
+) -> AddressActivity {
+    let chains_queried = chain_ids.len();
+    let deadline = Instant::now() + LOOKUP_TIMEOUT;
+    let mut results: Vec<(i32, ChainResult)> = futures::stream::iter(chain_ids)
+        .map(|chain_id| async move {
+            let result =
+                tokio::time::timeout_at(deadline, chain_activity(api, chain_id, address)).await;
+            let result = result.unwrap_or_else(|_| {
+                ChainResult::Failed(format!("lookup ran out of time after {:?}", LOOKUP_TIMEOUT))
+            });
+            (chain_id, result)
+        })
+        .buffer_unordered(MAX_CONCURRENT_CHAINS)
+        .collect()
+        .await;
+    results.sort_by_key(|(chain_id, _)| *chain_id);
+
+    let names = api.cached_chains.read().await;
+    let mut report = AddressActivity {
+        address: address.clone(),
+        chains_queried,
+        active: Vec::new(),
+        inactive: Vec::new(),
+        errors: BTreeMap::new(),
+    };
+    for (chain_id, result) in results {
+        match result {
+            ChainResult::Active(mut activity) => {
+                activity.chain_name = names.get(&chain_id).map(|chain| chain.get_name().into());
+                report.active.push(activity);
+            }
+            ChainResult::Inactive => report.inactive.push(chain_id),
+            ChainResult::Failed(e) => {
+                report.errors.insert(chain_id, e);
+            }
+        }
+    }
+    report.active.sort_by_key(|activity| {
+        std::cmp::Reverse(
+            activity
+                .transactions_count
+                .as_deref()
+                .and_then(|count| count.parse::<u64>().ok())
+                .unwrap_or_default(),
+        )
+    });
+    report
+}
+
+#[tokio::test]
+async fn test_address_activity() {
+    let mock = crate::mock_blockscout::MockBlockscout::start()
+        .await
+        .unwrap();
+    let api = mock.api();
+    let address: Address = "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045"
+        .parse()
+        .unwrap();
+
+    let chain_ids = candidate_chains(&api, false).await;
+    assert_eq!(chain_ids, vec![1, 100, 4200, 8453]);
+    assert!(candidate_chains(&api, true).await.contains(&11155111));
+
+    let report = address_activity(&api, &address, vec![1, 100, 999, 8453]).await;
+    assert_eq!(report.chains_queried, 4);
+    let active: Vec<i32> = report.active.iter().map(|a| a.chain_id).collect();
+    assert_eq!(active, vec![1, 8453]);
+    assert_eq!(report.active[0].chain_name.as_deref(), Some("Ethereum"));
+    assert_eq!(report.active[0].transactions_count.as_deref(), Some("1021"));
+    assert_eq!(report.inactive, vec![100]);
+    assert_eq!(report.errors.keys().copied().collect::<Vec<_>>(), vec![999]);
+}
@@ -0,0 +1,513 @@
+use crate::auth::{ApiKeys, extract_bearer, unauthorized};
+use anyhow::{Result, anyhow};
+use axum::{
+    Json,
+    extract::{Request, State},
+    http::{StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use jsonwebtoken::{
+    Algorithm, DecodingKey, Validation, decode, decode_header,
+    jwk::{Jwk, JwkSet},
+};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+pub const ISSUER_ENV: &str = "MCP_OAUTH_ISSUER";
+pub const RESOURCE_ENV: &str = "MCP_OAUTH_RESOURCE";
+pub const AUDIENCE_ENV: &str = "MCP_OAUTH_AUDIENCE";
+pub const SCOPES_ENV: &str = "MCP_OAUTH_SCOPES";
+pub const JWKS_FILE_ENV: &str = "MCP_OAUTH_JWKS_FILE";
+pub const JWKS_URL_ENV: &str = "MCP_OAUTH_JWKS_URL";
+pub const ALGORITHMS_ENV: &str = "MCP_OAUTH_ALGORITHMS";
+
+pub const METADATA_PATH: &str = "/.well-known/oauth-protected-resource";
+
+const JWKS_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Clone)]
+pub enum JwksSource {
+    File(String),
+    Url(String),
+}
+
+#[derive(Clone)]
+pub struct OAuthConfig {
+    pub issuer: String,
+    pub resource: String,
+    pub audience: String,
+    pub scopes: Vec<String>,
+    pub jwks_source: JwksSource,
+    // Accepted for keys whose jwk does not name an algorithm, a jwk `alg` always wins.
+    pub algorithms: Vec<Algorithm>,
+}
+
+impl OAuthConfig {
+    // Returns None when no issuer is configured, i.e. OAuth is disabled.
+    pub fn from_env() -> Result<Option<Self>> {
+        let issuer = match std::env::var(ISSUER_ENV) {
+            Ok(issuer) if !issuer.trim().is_empty() => issuer.trim().to_string(),
+            _ => return Ok(None),
+        };
+        let resource = std::env::var(RESOURCE_ENV)
+            .map_err(|_| anyhow!("{} is required when {} is set", RESOURCE_ENV, ISSUER_ENV))?;
+        let audience = std::env::var(AUDIENCE_ENV).unwrap_or_else(|_| resource.clone());
+        let scopes = std::env::var(SCOPES_ENV)
+            .unwrap_or_default()
+            .split([' ', ','])
+            .filter(|scope| !scope.is_empty())
+            .map(String::from)
+            .collect();
+        let jwks_source = match (std::env::var(JWKS_FILE_ENV), std::env::var(JWKS_URL_ENV)) {
+            (Ok(path), _) => JwksSource::File(path),
+            (_, Ok(url)) => JwksSource::Url(url),
+            _ => JwksSource::Url(format!(
+                "{}/.well-known/jwks.json",
+                issuer.trim_end_matches('/')
+            )),
+        };
+        let algorithms = match std::env::var(ALGORITHMS_ENV) {
+            Ok(algorithms) if !algorithms.trim().is_empty() => algorithms
+                .split([' ', ','])
+                .filter(|alg| !alg.is_empty())
+                .map(|alg| {
+                    Algorithm::from_str(alg)
+                        .map_err(|_| anyhow!("invalid algorithm in {}: {}", ALGORITHMS_ENV, alg))
+                })
+                .collect::<Result<_>>()?,
+            _ => vec![Algorithm::RS256],
+        };
+        Ok(Some(OAuthConfig {
+            issuer,
+            resource,
+            audience,
+            scopes,
+            jwks_source,
+            algorithms,
+        }))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ProtectedResourceMetadata {
+    pub resource: String,
+    pub authorization_servers: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub scopes_supported: Vec<String>,
+    pub bearer_methods_supported: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Claims {
+    pub sub: Option<String>,
+    #[serde(default)]
+    pub scope: String,
+    #[serde(default, deserialize_with = "scope_list")]
+    pub scp: Vec<String>,
+}
+
+// Issuers send `scp` as a list or, like `scope`, as one space separated string.
+fn scope_list<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Scopes {
+        Text(String),
+        List(Vec<String>),
+    }
+    Ok(match Scopes::deserialize(deserializer)? {
+        Scopes::Text(text) => text.split(' ').map(String::from).collect(),
+        Scopes::List(list) => list,
+    })
+}
+
+impl Claims {
+    pub fn scopes(&self) -> Vec<&str> {
+        self.scope
+            .split(' ')
+            .chain(self.scp.iter().map(String::as_str))
+            .filter(|scope| !scope.is_empty())
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub enum TokenError {
+    Invalid(String),
+    InsufficientScope,
+}
+
+struct Jwks {
+    set: JwkSet,
+    fetched_at: Instant,
+}
+
+#[derive(Clone)]
+pub struct ProtectedResource {
+    config: Arc<OAuthConfig>,
+    jwks: Arc<RwLock<Jwks>>,
+    api_keys: ApiKeys,
+}
+
+async fn load_jwks(source: &JwksSource) -> Result<JwkSet> {
+    match source {
+        JwksSource::File(path) => {
+            let content = std::fs::read_to_string(path)
+                .map_err(|e| anyhow!("failed to read jwks file {}: {}", path, e))?;
+            Ok(serde_json::from_str(&content)?)
+        }
+        JwksSource::Url(url) => {
+            let res = reqwest::Client::new().get(url).send().await?;
+            if res.status() != StatusCode::OK {
+                return Err(anyhow!("request failed: {}", res.status()));
+            }
+            Ok(res.json().await?)
+        }
+    }
+}
+
+impl ProtectedResource {
+    pub async fn new(config: OAuthConfig, api_keys: ApiKeys) -> Result<Self> {
+        let set = load_jwks(&config.jwks_source).await?;
+        Ok(Self::with_jwks(config, set, api_keys))
+    }
+
+    pub fn with_jwks(config: OAuthConfig, set: JwkSet, api_keys: ApiKeys) -> Self {
+        ProtectedResource {
+            config: Arc::new(config),
+            jwks: Arc::new(RwLock::new(Jwks {
+                set,
+                fetched_at: Instant::now(),
+            })),
+            api_keys,
+        }
+    }
+
+    pub fn metadata(&self) -> ProtectedResourceMetadata {
+        ProtectedResourceMetadata {
+            resource: self.config.resource.clone(),
+            authorization_servers: vec![self.config.issuer.clone()],
+            scopes_supported: self.config.scopes.clone(),
+            bearer_methods_supported: vec!["header".into()],
+        }
+    }
+
+    pub fn metadata_url(&self) -> String {
+        let resource = self.config.resource.trim_end_matches('/');
+        match resource.find("://").map(|i| i + 3) {
+            Some(start) => {
+                let end = resource[start..]
+                    .find('/')
+                    .map(|i| start + i)
+                    .unwrap_or(resource.len());
+                format!("{}{}{}", &resource[..end], METADATA_PATH, &resource[end..])
+            }
+            None => format!("{}{}", resource, METADATA_PATH),
+        }
+    }
+
+    // The algorithms a key may verify, never taken from the token itself.
+    fn key_algorithms(&self, jwk: &Jwk) -> Vec<Algorithm> {
+        match jwk.common.key_algorithm {
+            Some(alg) => Algorithm::from_str(&alg.to_string()).into_iter().collect(),
+            None => self.config.algorithms.clone(),
+        }
+    }
+
+    async fn find_key(&self, kid: Option<&str>) -> Option<(DecodingKey, Vec<Algorithm>)> {
+        let find = |set: &JwkSet| {
+            let jwk = match kid {
+                Some(kid) => set.find(kid),
+                None if set.keys.len() == 1 => set.keys.first(),
+                None => None,
+            }?;
+            let key = DecodingKey::from_jwk(jwk).ok()?;
+            Some((key, self.key_algorithms(jwk)))
+        };
+        {
+            let jwks = self.jwks.read().await;
+            if let Some(key) = find(&jwks.set) {
+                return Some(key);
+            }
+            if jwks.fetched_at.elapsed() < JWKS_REFRESH_INTERVAL {
+                return None;
+            }
+        }
+        // The issuer may have rotated its keys, refresh at most once per interval. The
+        // refresh is claimed under the lock but fetched outside it, so validations keep
+        // reading the current set meanwhile.
+        {
+            let mut jwks = self.jwks.write().await;
+            if jwks.fetched_at.elapsed() < JWKS_REFRESH_INTERVAL {
+                return find(&jwks.set);
+            }
+            jwks.fetched_at = Instant::now();
+        }
+        let set = load_jwks(&self.config.jwks_source).await;
+        let mut jwks = self.jwks.write().await;
+        match set {
+            Ok(set) => jwks.set = set,
+            Err(e) => tracing::warn!("failed to refresh jwks: {}", e),
+        }
+        find(&jwks.set)
+    }
+
+    pub async fn validate(&self, token: &str) -> Result<Claims, TokenError> {
+        let header = decode_header(token).map_err(|e| TokenError::Invalid(e.to_string()))?;
+        let (key, algorithms) = self
+            .find_key(header.kid.as_deref())
+            .await
+            .ok_or_else(|| TokenError::Invalid("unknown signing key".into()))?;
+        if !algorithms.contains(&header.alg) {
+            return Err(TokenError::Invalid(format!(
+                "algorithm {:?} is not allowed for this key",
+                header.alg
+            )));
+        }
+
+        let mut validation = Validation::new(header.alg);
+        validation.algorithms = algorithms;
+        validation.set_issuer(&[&self.config.issuer]);
+        validation.set_audience(&[&self.config.audience]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud"]);
+        let claims = decode::<Claims>(token, &key, &validation)
+            .map_err(|e| TokenError::Invalid(e.to_string()))?
+            .claims;
+
+        let granted = claims.scopes();
+        if self
+            .config
+            .scopes
+            .iter()
+            .any(|scope| !granted.contains(&scope.as_str()))
+        {
+            return Err(TokenError::InsufficientScope);
+        }
+        Ok(claims)
+    }
+
+    fn challenge(&self, error: Option<&str>) -> String {
+        let mut challenge = format!("Bearer resource_metadata=\"{}\"", self.metadata_url());
+        if !self.config.scopes.is_empty() {
+            challenge.push_str(&format!(", scope=\"{}\"", self.config.scopes.join(" ")));
+        }
+        if let Some(error) = error {
+            challenge.push_str(&format!(", error=\"{}\"", error));
+        }
+        challenge
+    }
+}
+
+pub async fn protected_resource_metadata(
+    State(resource): State<ProtectedResource>,
+) -> Json<ProtectedResourceMetadata> {
+    Json(resource.metadata())
+}
+
+pub async fn require_access_token(
+    State(resource): State<ProtectedResource>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let token = match extract_bearer(request.headers()) {
+        Some(token) => token,
+        None => {
+            return unauthorized(resource.challenge(None), "missing access token");
+        }
+    };
+    if !resource.api_keys.is_empty() && resource.api_keys.verify(token) {
+        return next.run(request).await;
+    }
+    match resource.validate(token).await {
+        Ok(claims) => {
+            tracing::debug!("access token accepted for {:?}", claims.sub);
+            next.run(request).await
+        }
+        Err(TokenError::Invalid(e)) => {
+            tracing::debug!("access token rejected: {}", e);
+            unauthorized(
+                resource.challenge(Some("invalid_token")),
+                "invalid access token",
+            )
+        }
+        Err(TokenError::InsufficientScope) => (
+            StatusCode::FORBIDDEN,
+            [(
+                header::WWW_AUTHENTICATE,
+                resource.challenge(Some("insufficient_scope")),
+            )],
+            "insufficient scope",
+        )
+            .into_response(),
+    }
+}
+
+#[tokio::test]
+async fn test_validate() {
+    use jsonwebtoken::{EncodingKey, Header, encode};
+
+    let secret = b"0123456789abcdef0123456789abcdef";
+    let jwks: JwkSet = serde_json::from_value(serde_json::json!({
+        "keys": [{"kty": "oct", "kid": "test", "alg": "HS256", "k": "MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY"}]
+    }))
+    .unwrap();
+    let config = OAuthConfig {
+        issuer: "https://issuer.example".into(),
+        resource: "https://mcp.example/mcp".into(),
+        audience: "https://mcp.example/mcp".into(),
+        scopes: vec!["mcp:tools".into()],
+        jwks_source: JwksSource::File("/nonexistent".into()),
+        algorithms: vec![jsonwebtoken::Algorithm::RS256],
+    };
+    let resource = ProtectedResource::with_jwks(config, jwks, ApiKeys::default());
+    assert_eq!(
+        resource.metadata_url(),
+        "https://mcp.example/.well-known/oauth-protected-resource/mcp"
+    );
+
+    let token_with = |alg: jsonwebtoken::Algorithm, aud: &str, scope: &str| {
+        let mut header = Header::new(alg);
+        header.kid = Some("test".into());
+        let claims = serde_json::json!({
+            "iss": "https://issuer.example",
+            "aud": aud,
+            "sub": "alice",
+            "scope": scope,
+            "exp": jsonwebtoken::get_current_timestamp() + 60,
+        });
+        encode(&header, &claims, &EncodingKey::from_secret(secret)).unwrap()
+    };
+    let token = |aud: &str, scope: &str| token_with(jsonwebtoken::Algorithm::HS256, aud, scope);
+
+    let claims = resource
+        .validate(&token("https://mcp.example/mcp", "openid mcp:tools"))
+        .await
+        .unwrap();
+    assert_eq!(claims.sub.as_deref(), Some("alice"));
+    assert!(matches!(
+        resource
+            .validate(&token("https://other.example", "mcp:tools"))
+            .await,
+        Err(TokenError::Invalid(_))
+    ));
+    assert!(matches!(
+        resource
+            .validate(&token("https://mcp.example/mcp", "openid"))
+            .await,
+        Err(TokenError::InsufficientScope)
+    ));
+    // The key is pinned to HS256 by its jwk, whatever the token header says.
+    assert!(matches!(
+        resource
+            .validate(&token_with(
+                jsonwebtoken::Algorithm::HS384,
+                "https://mcp.example/mcp",
+                "mcp:tools"
+            ))
+            .await,
+        Err(TokenError::Invalid(_))
+    ));
+}
+
+#[test]
+fn test_scopes() {
+    let scopes = |claims: serde_json::Value| -> Vec<String> {
+        let claims: Claims = serde_json::from_value(claims).unwrap();
+        claims.scopes().into_iter().map(String::from).collect()
+    };
+    assert_eq!(
+        scopes(serde_json::json!({"scp": "openid mcp:tools"})),
+        vec!["openid", "mcp:tools"]
+    );
+    assert_eq!(
+        scopes(serde_json::json!({"scope": "openid", "scp": ["mcp:tools"]})),
+        vec!["openid", "mcp:tools"]
+    );
+    assert!(scopes(serde_json::json!({})).is_empty());
+}
+
+#[tokio::test]
+async fn test_require_access_token() {
+    use jsonwebtoken::{EncodingKey, Header, encode};
+
+    let jwks: JwkSet = serde_json::from_value(serde_json::json!({
+        "keys": [{"kty": "oct", "kid": "test", "alg": "HS256", "k": "MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY"}]
+    }))
+    .unwrap();
+    let config = OAuthConfig {
+        issuer: "https://issuer.example".into(),
+        resource: "https://mcp.example/mcp".into(),
+        audience: "https://mcp.example/mcp".into(),
+        scopes: vec!["mcp:tools".into()],
+        jwks_source: JwksSource::File("/nonexistent".into()),
+        algorithms: vec![Algorithm::RS256],
+    };
+    let resource = ProtectedResource::with_jwks(config, jwks, ApiKeys::new(vec!["key".into()]));
+    let router = axum::Router::new()
+        .route("/mcp", axum::routing::post(|| async { "ok" }))
+        .route_layer(axum::middleware::from_fn_with_state(
+            resource,
+            require_access_token,
+        ));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, router).await });
+
+    let token = |scp: &str| {
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = Some("test".into());
+        let claims = serde_json::json!({
+            "iss": "https://issuer.example",
+            "aud": "https://mcp.example/mcp",
+            "scp": scp,
+            "exp": jsonwebtoken::get_current_timestamp() + 60,
+        });
+        encode(
+            &header,
+            &claims,
+            &EncodingKey::from_secret(b"0123456789abcdef0123456789abcdef"),
+        )
+        .unwrap()
+    };
+    let call = |bearer: Option<String>| {
+        let url = url.clone();
+        async move {
+            let mut req = reqwest::Client::new().post(url);
+            if let Some(bearer) = bearer {
+                req = req.bearer_auth(bearer);
+            }
+            let res = req.send().await.unwrap();
+            let challenge = res
+                .headers()
+                .get(header::WWW_AUTHENTICATE)
+                .map(|value| value.to_str().unwrap().to_string());
+            (res.status(), challenge)
+        }
+    };
+    let challenge = |error: &str| {
+        let mut challenge = "Bearer resource_metadata=\"https://mcp.example/.well-known/oauth-protected-resource/mcp\", scope=\"mcp:tools\"".to_string();
+        if !error.is_empty() {
+            challenge.push_str(&format!(", error=\"{}\"", error));
+        }
+        Some(challenge)
+    };
+
+    assert_eq!(call(None).await, (StatusCode::UNAUTHORIZED, challenge("")));
+    assert_eq!(
+        call(Some("not a token".into())).await,
+        (StatusCode::UNAUTHORIZED, challenge("invalid_token"))
+    );
+    assert_eq!(
+        call(Some(token("openid"))).await,
+        (StatusCode::FORBIDDEN, challenge("insufficient_scope"))
+    );
+    assert_eq!(
+        call(Some(token("openid mcp:tools"))).await,
+        (StatusCode::OK, None)
+    );
+    assert_eq!(call(Some("key".into())).await, (StatusCode::OK, None));
+}
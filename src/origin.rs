@@ -0,0 +1,176 @@
+use crate::auth::API_KEY_HEADER;
+use axum::{
+    extract::{Request, State},
+    http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+pub const ALLOWED_ORIGINS_ENV: &str = "MCP_ALLOWED_ORIGINS";
+pub const ALLOWED_HOSTS_ENV: &str = "MCP_ALLOWED_HOSTS";
+
+const LOCAL_HOSTS: &[&str] = &["localhost", "127.0.0.1", "[::1]"];
+
+// Guards against DNS rebinding. A cross origin request carries an Origin and is turned
+// away unless allowed, but a page on a rebound domain makes same origin requests, often
+// without one, so the Host header is checked too: only local names unless configured.
+#[derive(Clone, Debug, Default)]
+pub struct OriginPolicy {
+    origins: Arc<Vec<String>>,
+    hosts: Arc<Vec<String>>,
+}
+
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().trim_end_matches('/').to_ascii_lowercase())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+// Strips the port, keeping the brackets of an ipv6 literal.
+fn host_name(host: &str) -> &str {
+    match host.rfind(':') {
+        Some(i) if !host[i..].contains(']') => &host[..i],
+        _ => host,
+    }
+}
+
+impl OriginPolicy {
+    // Both are comma separated. Origins are full `scheme://host[:port]` values, hosts are
+    // matched without the port. Local origins are always allowed, `*` allows any.
+    pub fn new(origins: &str, hosts: &str) -> Self {
+        OriginPolicy {
+            origins: Arc::new(parse_list(origins)),
+            hosts: Arc::new(parse_list(hosts)),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(
+            &std::env::var(ALLOWED_ORIGINS_ENV).unwrap_or_default(),
+            &std::env::var(ALLOWED_HOSTS_ENV).unwrap_or_default(),
+        )
+    }
+
+    // Any host is accepted only when explicitly allowed with `*`.
+    pub fn restricts_hosts(&self) -> bool {
+        !self.hosts.iter().any(|host| host == "*")
+    }
+
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        let origin = origin.trim_end_matches('/').to_ascii_lowercase();
+        if self
+            .origins
+            .iter()
+            .any(|allowed| allowed == "*" || *allowed == origin)
+        {
+            return true;
+        }
+        match origin.split_once("://") {
+            Some(("http" | "https", host)) => LOCAL_HOSTS.contains(&host_name(host)),
+            _ => false,
+        }
+    }
+
+    pub fn allows_host(&self, host: &str) -> bool {
+        if !self.restricts_hosts() {
+            return true;
+        }
+        let host = host_name(host).to_ascii_lowercase();
+        LOCAL_HOSTS.contains(&host.as_str()) || self.hosts.contains(&host)
+    }
+
+    pub fn check(&self, headers: &HeaderMap) -> Result<(), &'static str> {
+        if let Some(origin) = headers.get(header::ORIGIN) {
+            match origin.to_str() {
+                Ok(origin) if self.allows_origin(origin) => {}
+                _ => return Err("origin not allowed"),
+            }
+        }
+        if self.restricts_hosts() {
+            match headers.get(header::HOST).map(HeaderValue::to_str) {
+                Some(Ok(host)) if self.allows_host(host) => {}
+                _ => return Err("host not allowed"),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn cors_layer(&self) -> CorsLayer {
+        let policy = self.clone();
+        CorsLayer::new()
+            .allow_origin(AllowOrigin::predicate(move |origin, _| {
+                origin
+                    .to_str()
+                    .is_ok_and(|origin| policy.allows_origin(origin))
+            }))
+            .allow_methods([Method::GET, Method::POST, Method::DELETE])
+            .allow_headers([
+                header::AUTHORIZATION,
+                header::CONTENT_TYPE,
+                HeaderName::from_static(API_KEY_HEADER),
+                HeaderName::from_static("mcp-session-id"),
+                HeaderName::from_static("mcp-protocol-version"),
+                HeaderName::from_static("last-event-id"),
+            ])
+            .expose_headers([
+                header::WWW_AUTHENTICATE,
+                HeaderName::from_static("mcp-session-id"),
+            ])
+    }
+}
+
+pub async fn validate_origin(
+    State(policy): State<OriginPolicy>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if let Err(message) = policy.check(request.headers()) {
+        tracing::warn!(
+            "rejected request from origin {:?} host {:?}: {}",
+            request.headers().get(header::ORIGIN),
+            request.headers().get(header::HOST),
+            message
+        );
+        return (StatusCode::FORBIDDEN, message).into_response();
+    }
+    next.run(request).await
+}
+
+#[test]
+fn test_origin_policy() {
+    let open = OriginPolicy::default();
+    assert!(open.allows_origin("http://localhost:6274"));
+    assert!(open.allows_origin("http://[::1]:8080"));
+    assert!(!open.allows_origin("https://evil.example"));
+    assert!(!open.allows_origin("null"));
+    assert!(open.allows_host("localhost:8000"));
+    assert!(!open.allows_host("evil.example"));
+    assert!(OriginPolicy::new("", "*").allows_host("evil.example"));
+
+    let policy = OriginPolicy::new("https://app.example/", "mcp.example");
+    assert!(policy.allows_origin("https://APP.example"));
+    assert!(!policy.allows_origin("https://app.example.evil"));
+    assert!(policy.allows_host("mcp.example:443"));
+    assert!(policy.allows_host("127.0.0.1:8000"));
+    assert!(!policy.allows_host("localhost.evil"));
+
+    let mut headers = HeaderMap::new();
+    assert_eq!(policy.check(&headers), Err("host not allowed"));
+    headers.insert(header::HOST, HeaderValue::from_static("mcp.example"));
+    assert!(policy.check(&headers).is_ok());
+    headers.insert(
+        header::ORIGIN,
+        HeaderValue::from_static("https://evil.example"),
+    );
+    assert_eq!(policy.check(&headers), Err("origin not allowed"));
+    headers.insert(
+        header::ORIGIN,
+        HeaderValue::from_static("https://app.example"),
+    );
+    headers.insert(header::HOST, HeaderValue::from_static("rebound.example"));
+    assert_eq!(policy.check(&headers), Err("host not allowed"));
+}
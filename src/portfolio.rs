@@ -0,0 +1,228 @@
+use crate::block_scout_api::{API, GetAddressTokensParams, next_page};
+use crate::types::Address;
+use serde::Serialize;
+use serde_json::Value;
+
+// 50 tokens a page, enough for all but the most airdropped wallets.
+const MAX_PAGES: usize = 20;
+
+#[derive(Serialize, Debug)]
+pub struct Holding {
+    // `native` for the chain's coin, the token standard otherwise.
+    pub asset_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<Address>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    // In the asset's smallest unit, as a decimal string.
+    pub balance: String,
+    pub decimals: Option<i32>,
+    pub amount: Option<f64>,
+    pub exchange_rate: Option<f64>,
+    pub value_usd: Option<f64>,
+    // Why `value_usd` is missing, the asset is left out of the total.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unpriced_reason: Option<&'static str>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Portfolio {
+    pub address: Address,
+    // Of the priced assets only, see `unpriced`.
+    pub total_value_usd: f64,
+    pub native: Holding,
+    // Priced tokens by value, unpriced ones after them.
+    pub tokens: Vec<Holding>,
+    // Symbols (or addresses) of the assets without a USD value.
+    pub unpriced: Vec<String>,
+    pub pages_fetched: usize,
+    // More token pages were left, the portfolio is incomplete.
+    pub truncated: bool,
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::String(value) => value.parse().ok(),
+        value => value.as_f64(),
+    }
+}
+
+fn decimals(value: &Value) -> Option<i32> {
+    match value {
+        Value::String(value) => value.parse().ok(),
+        value => value.as_i64().and_then(|d| d.try_into().ok()),
+    }
+}
+
+fn holding(
+    asset_type: String,
+    balance: String,
+    decimals: Option<i32>,
+    exchange_rate: Option<f64>,
+) -> Holding {
+    let amount = decimals.and_then(|decimals| {
+        balance
+            .parse::<f64>()
+            .ok()
+            .map(|balance| balance / 10f64.powi(decimals))
+    });
+    let unpriced_reason = match (&amount, &exchange_rate) {
+        (_, None) => Some("no exchange rate"),
+        (None, Some(_)) => Some("unknown decimals"),
+        (Some(_), Some(_)) => None,
+    };
+    Holding {
+        asset_type,
+        token: None,
+        name: None,
+        symbol: None,
+        value_usd: amount
+            .zip(exchange_rate)
+            .map(|(amount, rate)| amount * rate),
+        balance,
+        decimals,
+        amount,
+        exchange_rate,
+        unpriced_reason,
+    }
+}
+
+fn token_holding(item: &Value) -> Option<Holding> {
+    let token = &item["token"];
+    let asset_type = token["type"].as_str().unwrap_or("unknown").to_string();
+    // NFTs have no decimals, a count of them is already the amount.
+    let decimals = match asset_type.as_str() {
+        "ERC-721" | "ERC-404" => decimals(&token["decimals"]).or(Some(0)),
+        _ => decimals(&token["decimals"]),
+    };
+    let mut holding = holding(
+        asset_type,
+        item["value"].as_str()?.to_string(),
+        decimals,
+        number(&token["exchange_rate"]),
+    );
+    holding.token = token["address_hash"].as_str()?.parse().ok();
+    holding.name = token["name"].as_str().map(String::from);
+    holding.symbol = token["symbol"].as_str().map(String::from);
+    Some(holding)
+}
+
+// The native balance and every token balance of `address` valued in USD with the
+// explorer's exchange rates. Assets without a rate or decimals are kept but flagged
+// and left out of the total, so it is a lower bound.
+pub async fn portfolio(api: &API, chain_id: i32, address: Address) -> anyhow::Result<Portfolio> {
+    let info = api.get_address_info(chain_id, address.clone()).await?;
+    let mut native = holding(
+        "native".into(),
+        info["coin_balance"].as_str().unwrap_or("0").to_string(),
+        Some(18),
+        number(&info["exchange_rate"]),
+    );
+    // An empty wallet is worth nothing whatever the rate.
+    if native.amount == Some(0.0) && native.value_usd.is_none() {
+        native.value_usd = Some(0.0);
+        native.unpriced_reason = None;
+    }
+
+    let mut tokens = Vec::new();
+    let mut page = Default::default();
+    let mut pages_fetched = 0;
+    let truncated = loop {
+        if pages_fetched == MAX_PAGES {
+            break true;
+        }
+        let params = GetAddressTokensParams {
+            page,
+            ..Default::default()
+        };
+        let items = api
+            .get_address_tokens(chain_id, address.clone(), params)
+            .await?;
+        pages_fetched += 1;
+        tokens.extend(
+            items["items"]
+                .as_array()
+                .map(Vec::as_slice)
+                .unwrap_or_default()
+                .iter()
+                .filter_map(token_holding),
+        );
+        match next_page(&items) {
+            Some(next) => page = next,
+            None => break false,
+        }
+    };
+    tokens.sort_by(|a, b| {
+        b.value_usd.is_some().cmp(&a.value_usd.is_some()).then(
+            b.value_usd
+                .unwrap_or_default()
+                .total_cmp(&a.value_usd.unwrap_or_default()),
+        )
+    });
+
+    let total_value_usd = std::iter::once(&native)
+        .chain(&tokens)
+        .filter_map(|holding| holding.value_usd)
+        .sum();
+    let unpriced = std::iter::once(&native)
+        .chain(&tokens)
+        .filter(|holding| holding.value_usd.is_none())
+        .map(|holding| {
+            holding
+                .symbol
+                .clone()
+                .or_else(|| holding.token.as_ref().map(Address::to_string))
+                .unwrap_or_else(|| holding.asset_type.clone())
+        })
+        .collect();
+    Ok(Portfolio {
+        address,
+        total_value_usd,
+        native,
+        tokens,
+        unpriced,
+        pages_fetched,
+        truncated,
+    })
+}
+
+#[tokio::test]
+async fn test_portfolio() {
+    let mock = crate::mock_blockscout::MockBlockscout::start()
+        .await
+        .unwrap();
+    let api = mock.api();
+    let address: Address = "0x4444444444444444444444444444444444444444"
+        .parse()
+        .unwrap();
+
+    let portfolio = portfolio(&api, 1, address).await.unwrap();
+    assert_eq!(portfolio.pages_fetched, 2);
+    assert!(!portfolio.truncated);
+    assert_eq!(portfolio.native.amount, Some(2.0));
+    assert_eq!(portfolio.native.value_usd, Some(5000.0));
+    let symbols: Vec<&str> = portfolio
+        .tokens
+        .iter()
+        .filter_map(|token| token.symbol.as_deref())
+        .collect();
+    assert_eq!(symbols, vec!["WETH", "USDC", "SCAM", "BAYC", "ODD"]);
+    assert_eq!(portfolio.tokens[1].amount, Some(1500.5));
+    assert_eq!(portfolio.total_value_usd, 9000.5);
+    assert_eq!(portfolio.unpriced, vec!["SCAM", "BAYC", "ODD"]);
+    assert_eq!(
+        portfolio.tokens[2].unpriced_reason,
+        Some("no exchange rate")
+    );
+    assert_eq!(portfolio.tokens[3].amount, Some(2.0));
+    assert_eq!(
+        portfolio.tokens[4].unpriced_reason,
+        Some("unknown decimals")
+    );
+    // The second page was asked for with the first one's `next_page_params`.
+    assert!(mock.requests().contains(
+        &"1:addresses/0x4444444444444444444444444444444444444444/tokens?id=2&items_count=50&value=1500500000".to_string()
+    ));
+}
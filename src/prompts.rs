@@ -0,0 +1,163 @@
+use rmcp::{
+    ErrorData as McpError,
+    model::{
+        GetPromptResult, JsonObject, Prompt, PromptArgument, PromptMessage, PromptMessageRole,
+    },
+};
+use serde_json::Value;
+
+pub const EXPLAIN_TRANSACTION: &str = "explain_transaction";
+pub const PROFILE_WALLET: &str = "profile_wallet";
+pub const AUDIT_TOKEN: &str = "audit_token";
+
+fn argument(name: &str, description: &str) -> PromptArgument {
+    PromptArgument {
+        name: name.into(),
+        description: Some(description.into()),
+        required: Some(true),
+    }
+}
+
+fn chain_id_argument() -> PromptArgument {
+    argument("chain_id", "the chain id to query")
+}
+
+pub fn list() -> Vec<Prompt> {
+    vec![
+        Prompt::new(
+            EXPLAIN_TRANSACTION,
+            Some("Explain what a transaction did, step by step"),
+            Some(vec![
+                chain_id_argument(),
+                argument("transaction_hash", "the transaction hash to explain"),
+            ]),
+        ),
+        Prompt::new(
+            PROFILE_WALLET,
+            Some("Profile a wallet: balances, holdings, activity and counterparties"),
+            Some(vec![
+                chain_id_argument(),
+                argument("address_hash", "the wallet address or name to profile"),
+            ]),
+        ),
+        Prompt::new(
+            AUDIT_TOKEN,
+            Some("Audit a token contract: metadata, supply distribution and transfer activity"),
+            Some(vec![
+                chain_id_argument(),
+                argument("token_address", "the token address to audit"),
+            ]),
+        ),
+    ]
+}
+
+fn get_argument(arguments: &Option<JsonObject>, name: &str) -> Result<String, McpError> {
+    let value = arguments.as_ref().and_then(|arguments| arguments.get(name));
+    let value = match value {
+        Some(Value::String(s)) => s.trim().to_string(),
+        Some(Value::Number(n)) => n.to_string(),
+        _ => String::new(),
+    };
+    if value.is_empty() {
+        return Err(McpError::invalid_params(
+            format!("missing required argument: {}", name),
+            None,
+        ));
+    }
+    Ok(value)
+}
+
+pub fn get(name: &str, arguments: &Option<JsonObject>) -> Result<GetPromptResult, McpError> {
+    let (description, text) = match name {
+        EXPLAIN_TRANSACTION => {
+            let chain_id = get_argument(arguments, "chain_id")?;
+            let hash = get_argument(arguments, "transaction_hash")?;
+            (
+                format!("Explain transaction {} on chain {}", hash, chain_id),
+                format!(
+                    "Explain what transaction {hash} on chain {chain_id} did.\n\
+                     \n\
+                     Call the tools in this order, always with chain_id={chain_id} and transaction_hash={hash}:\n\
+                     1. get_transaction_info - status, sender, receiver, value, fee and the decoded method call.\n\
+                     2. get_transaction_summary - Blockscout's human readable summary, if available.\n\
+                     3. get_transaction_token_transfers - every token that moved and between whom.\n\
+                     4. get_transaction_internal_transactions - native value moved by contract calls.\n\
+                     5. get_transaction_logs - events emitted, to confirm the effects seen above.\n\
+                     \n\
+                     Then write a short plain-language explanation: who initiated it, which contracts \
+                     were involved, what assets moved where (with amounts adjusted for token decimals), \
+                     whether it succeeded, and anything unusual such as a revert, a high fee or an \
+                     unverified contract."
+                ),
+            )
+        }
+        PROFILE_WALLET => {
+            let chain_id = get_argument(arguments, "chain_id")?;
+            let address = get_argument(arguments, "address_hash")?;
+            (
+                format!("Profile wallet {} on chain {}", address, chain_id),
+                format!(
+                    "Build a profile of wallet {address} on chain {chain_id}.\n\
+                     \n\
+                     Call the tools in this order, always with chain_id={chain_id} and address_hash={address}:\n\
+                     1. get_address_info - native balance, contract or EOA, name tags.\n\
+                     2. get_address_counters - transaction, transfer and gas usage counts.\n\
+                     3. get_address_tokens - fungible token holdings.\n\
+                     4. get_address_nft_collections - NFT holdings grouped by collection.\n\
+                     5. get_address_transactions - latest transactions.\n\
+                     6. get_address_token_transfers - latest token transfers.\n\
+                     7. get_address_coin_balance_history_by_day - how the native balance evolved.\n\
+                     \n\
+                     Then summarize: what kind of account this is, what it holds, how active it is \
+                     and since when, its most frequent counterparties and contracts, and any \
+                     noteworthy patterns such as large inflows or outflows."
+                ),
+            )
+        }
+        AUDIT_TOKEN => {
+            let chain_id = get_argument(arguments, "chain_id")?;
+            let token = get_argument(arguments, "token_address")?;
+            (
+                format!("Audit token {} on chain {}", token, chain_id),
+                format!(
+                    "Audit token {token} on chain {chain_id}.\n\
+                     \n\
+                     Call the tools in this order, always with chain_id={chain_id}:\n\
+                     1. get_token_info with token_address={token} - name, symbol, type, decimals, supply, price.\n\
+                     2. get_token_counters with token_address={token} - holder and transfer counts.\n\
+                     3. get_token_holders with token_address={token} - concentration of the supply.\n\
+                     4. get_token_transfers with token_address={token} - recent transfer activity.\n\
+                     5. get_address_info with address_hash={token} - whether the contract is verified, \
+                     its creator and creation transaction.\n\
+                     \n\
+                     Then report: what the token is, how concentrated the holdings are (share held by \
+                     the top holders), whether the contract is verified, any suspicious activity in the \
+                     recent transfers, and an overall risk assessment."
+                ),
+            )
+        }
+        _ => {
+            return Err(McpError::invalid_params(
+                format!("unknown prompt: {}", name),
+                None,
+            ));
+        }
+    };
+    Ok(GetPromptResult {
+        description: Some(description),
+        messages: vec![PromptMessage::new_text(PromptMessageRole::User, text)],
+    })
+}
+
+#[test]
+fn test_get_prompt() {
+    let mut arguments = JsonObject::new();
+    arguments.insert("chain_id".into(), Value::from(1));
+    arguments.insert("transaction_hash".into(), Value::from("0xabc"));
+    let r = get(EXPLAIN_TRANSACTION, &Some(arguments)).unwrap();
+    assert_eq!(r.messages.len(), 1);
+
+    assert!(get(PROFILE_WALLET, &None).is_err());
+    assert!(get("unknown", &None).is_err());
+    assert_eq!(list().len(), 3);
+}
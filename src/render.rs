@@ -0,0 +1,303 @@
+use crate::call_tree::CallTree;
+use crate::fund_flow::FundFlow;
+use rmcp::schemars;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphFormat {
+    #[default]
+    Json,
+    Dot,
+    Mermaid,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Node {
+    pub id: String,
+    pub label: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    pub label: String,
+}
+
+// A directed graph of addresses, built from explorer responses and rendered as
+// Graphviz DOT or a Mermaid flowchart. Nodes keep the order they were first seen in.
+#[derive(Debug, Default)]
+pub struct Graph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+    index: HashMap<String, usize>,
+}
+
+// `vitalik.eth (0xd8dA…6045)` or just the shortened address.
+fn address_label(address: &str, name: Option<&str>) -> String {
+    let short = match (
+        address.get(..6),
+        address.get(address.len().saturating_sub(4)..),
+    ) {
+        (Some(head), Some(tail)) if address.len() > 12 => format!("{}…{}", head, tail),
+        _ => address.to_string(),
+    };
+    match name {
+        Some(name) => format!("{} ({})", name, short),
+        None => short,
+    }
+}
+
+fn format_amount(value: &str, decimals: i32) -> String {
+    match value.parse::<f64>() {
+        Ok(value) => (value / 10f64.powi(decimals)).to_string(),
+        Err(_) => value.to_string(),
+    }
+}
+
+fn party(value: &Value) -> Option<(&str, Option<&str>)> {
+    let name = value["ens_domain_name"]
+        .as_str()
+        .or_else(|| value["name"].as_str());
+    Some((value["hash"].as_str()?, name))
+}
+
+fn items(page: &Value) -> &[Value] {
+    page["items"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+impl Graph {
+    pub fn add_node(&mut self, address: &str, name: Option<&str>) {
+        if !self.index.contains_key(address) {
+            self.index.insert(address.to_string(), self.nodes.len());
+            self.nodes.push(Node {
+                id: address.to_string(),
+                label: address_label(address, name),
+            });
+        }
+    }
+
+    pub fn add_edge(&mut self, from: &str, to: &str, label: String) {
+        self.add_node(from, None);
+        self.add_node(to, None);
+        self.edges.push(Edge {
+            from: from.to_string(),
+            to: to.to_string(),
+            label,
+        });
+    }
+
+    pub fn from_fund_flow(flow: &FundFlow) -> Self {
+        let mut graph = Graph::default();
+        for node in &flow.nodes {
+            graph.add_node(node.address.as_str(), node.name.as_deref());
+        }
+        for edge in &flow.edges {
+            let amount = edge
+                .amount
+                .map(|amount| amount.to_string())
+                .or_else(|| edge.token_id.as_ref().map(|id| format!("#{}", id)))
+                .unwrap_or_default();
+            let asset = edge.symbol.as_deref().unwrap_or(&edge.asset);
+            graph.add_edge(
+                edge.from.as_str(),
+                edge.to.as_str(),
+                format!("{} {}", amount, asset).trim().to_string(),
+            );
+        }
+        graph
+    }
+
+    // A page of token transfers, as returned for an address or a transaction.
+    pub fn from_token_transfers(page: &Value) -> Self {
+        let mut graph = Graph::default();
+        for item in items(page) {
+            let (Some((from, from_name)), Some((to, to_name))) =
+                (party(&item["from"]), party(&item["to"]))
+            else {
+                continue;
+            };
+            graph.add_node(from, from_name);
+            graph.add_node(to, to_name);
+            let token = &item["token"];
+            let symbol = token["symbol"].as_str().unwrap_or("?");
+            let decimals = token["decimals"]
+                .as_str()
+                .and_then(|decimals| decimals.parse().ok())
+                .unwrap_or_default();
+            let total = &item["total"];
+            let label = match (total["value"].as_str(), total["token_id"].as_str()) {
+                (Some(value), _) => format!("{} {}", format_amount(value, decimals), symbol),
+                (None, Some(token_id)) => format!("{} #{}", symbol, token_id),
+                (None, None) => symbol.to_string(),
+            };
+            graph.add_edge(from, to, label);
+        }
+        graph
+    }
+
+    // A page of internal transactions, edges numbered in execution order.
+    pub fn from_internal_transactions(page: &Value) -> Self {
+        let mut graph = Graph::default();
+        for item in items(page) {
+            let Some((from, from_name)) = party(&item["from"]) else {
+                continue;
+            };
+            // Contract creations name the new contract instead of `to`.
+            let Some((to, to_name)) =
+                party(&item["to"]).or_else(|| party(&item["created_contract"]))
+            else {
+                continue;
+            };
+            graph.add_node(from, from_name);
+            graph.add_node(to, to_name);
+            let mut label = format!(
+                "#{} {}",
+                item["index"].as_u64().unwrap_or_default(),
+                item["type"].as_str().unwrap_or("call")
+            );
+            if let Some(value) = item["value"].as_str().filter(|value| *value != "0") {
+                write!(label, " {} native", format_amount(value, 18)).unwrap();
+            }
+            if item["success"] == Value::Bool(false) {
+                label.push_str(" (failed)");
+            }
+            graph.add_edge(from, to, label);
+        }
+        graph
+    }
+
+    // Every call of the tree as an edge, numbered depth first.
+    pub fn from_call_tree(tree: &CallTree) -> Self {
+        let mut graph = Graph::default();
+        for (i, call) in tree.root.flatten().into_iter().enumerate() {
+            let (Some(from), Some(to)) = (&call.from, &call.to) else {
+                continue;
+            };
+            graph.add_node(from.as_str(), call.from_name.as_deref());
+            graph.add_node(to.as_str(), call.to_name.as_deref());
+            let mut label = format!("#{} {}", i, call.call_type);
+            if let Some(method) = call.method.as_ref().or(call.selector.as_ref()) {
+                write!(label, " {}", method).unwrap();
+            }
+            if call.value != "0" {
+                write!(label, " {} native", format_amount(&call.value, 18)).unwrap();
+            }
+            if !call.success {
+                label.push_str(" (failed)");
+            }
+            graph.add_edge(from.as_str(), to.as_str(), label);
+        }
+        graph
+    }
+
+    pub fn to_dot(&self) -> String {
+        let quote = |text: &str| format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""));
+        let mut dot = String::from("digraph {\n  rankdir=LR;\n  node [shape=box];\n");
+        for node in &self.nodes {
+            writeln!(dot, "  {} [label={}];", quote(&node.id), quote(&node.label)).unwrap();
+        }
+        for edge in &self.edges {
+            writeln!(
+                dot,
+                "  {} -> {} [label={}];",
+                quote(&edge.from),
+                quote(&edge.to),
+                quote(&edge.label)
+            )
+            .unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn to_mermaid(&self) -> String {
+        // Mermaid ids must be plain words, labels go in quotes with `#` and quotes
+        // written as entity codes.
+        let quote =
+            |text: &str| format!("\"{}\"", text.replace('#', "#35;").replace('"', "#quot;"));
+        let mut mermaid = String::from("flowchart LR\n");
+        for (i, node) in self.nodes.iter().enumerate() {
+            writeln!(mermaid, "  n{}[{}]", i, quote(&node.label)).unwrap();
+        }
+        for edge in &self.edges {
+            writeln!(
+                mermaid,
+                "  n{} -->|{}| n{}",
+                self.index[&edge.from],
+                quote(&edge.label),
+                self.index[&edge.to]
+            )
+            .unwrap();
+        }
+        mermaid
+    }
+
+    pub fn render(&self, format: GraphFormat) -> Option<String> {
+        match format {
+            GraphFormat::Json => None,
+            GraphFormat::Dot => Some(self.to_dot()),
+            GraphFormat::Mermaid => Some(self.to_mermaid()),
+        }
+    }
+}
+
+#[test]
+fn test_render() {
+    use serde_json::json;
+
+    let page = json!({"items": [{
+        "from": {"hash": "0x0000000000000000000000000000000000000000", "name": null},
+        "to": {"hash": "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045", "ens_domain_name": "vitalik.eth"},
+        "token": {"symbol": "WETH", "decimals": "18"},
+        "total": {"value": "1500000000000000000", "decimals": "18"},
+    }, {
+        "from": {"hash": "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045"},
+        "to": {"hash": "0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D", "name": "Say \"hi\""},
+        "token": {"symbol": "BAYC"},
+        "total": {"token_id": "42"},
+    }]});
+    let graph = Graph::from_token_transfers(&page);
+    assert_eq!(
+        graph.to_dot(),
+        r#"digraph {
+  rankdir=LR;
+  node [shape=box];
+  "0x0000000000000000000000000000000000000000" [label="0x0000…0000"];
+  "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045" [label="vitalik.eth (0xd8dA…6045)"];
+  "0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D" [label="Say \"hi\" (0xBC4C…f13D)"];
+  "0x0000000000000000000000000000000000000000" -> "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045" [label="1.5 WETH"];
+  "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045" -> "0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D" [label="BAYC #42"];
+}
+"#
+    );
+    assert_eq!(
+        graph.to_mermaid(),
+        r#"flowchart LR
+  n0["0x0000…0000"]
+  n1["vitalik.eth (0xd8dA…6045)"]
+  n2["Say #quot;hi#quot; (0xBC4C…f13D)"]
+  n0 -->|"1.5 WETH"| n1
+  n1 -->|"BAYC #35;42"| n2
+"#
+    );
+
+    let page = json!({"items": [{
+        "index": 1,
+        "type": "call",
+        "from": {"hash": "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2", "name": "WETH9"},
+        "to": {"hash": "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045"},
+        "value": "2000000000000000000",
+        "success": false,
+    }]});
+    let graph = Graph::from_internal_transactions(&page);
+    assert_eq!(graph.edges[0].label, "#1 call 2 native (failed)");
+    assert_eq!(graph.nodes[0].label, "WETH9 (0xC02a…6Cc2)");
+    assert!(graph.render(GraphFormat::Json).is_none());
+}
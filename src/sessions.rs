@@ -0,0 +1,431 @@
+use crate::types::keccak256;
+use anyhow::{Result, anyhow};
+use axum::{
+    Json,
+    extract::{Request, State},
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use futures::Stream;
+use rmcp::{
+    model::{ClientJsonRpcMessage, ServerJsonRpcMessage},
+    transport::{
+        StreamableHttpServerConfig,
+        common::server_side_http::ServerSseMessage,
+        streamable_http_server::session::{
+            SessionId, SessionManager,
+            local::{LocalSessionManager, LocalSessionManagerError},
+        },
+    },
+};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+pub const IDLE_TIMEOUT_ENV: &str = "MCP_SESSION_IDLE_TIMEOUT_SECS";
+pub const MAX_SESSIONS_ENV: &str = "MCP_MAX_SESSIONS";
+pub const SSE_KEEP_ALIVE_ENV: &str = "MCP_SSE_KEEP_ALIVE_SECS";
+pub const STATELESS_ENV: &str = "MCP_STATELESS";
+
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+const DEFAULT_SSE_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+#[derive(Clone, Debug)]
+pub struct SessionSettings {
+    pub idle_timeout: Option<Duration>,
+    pub max_sessions: Option<usize>,
+    pub sse_keep_alive: Option<Duration>,
+    pub stateless: bool,
+}
+
+impl Default for SessionSettings {
+    fn default() -> Self {
+        SessionSettings {
+            idle_timeout: Some(DEFAULT_IDLE_TIMEOUT),
+            max_sessions: None,
+            sse_keep_alive: Some(DEFAULT_SSE_KEEP_ALIVE),
+            stateless: false,
+        }
+    }
+}
+
+// Zero means disabled for the durations and unlimited for the session cap.
+fn env_u64(name: &str) -> Result<Option<u64>> {
+    match std::env::var(name) {
+        Ok(value) => value
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| anyhow!("{} must be a non-negative integer", name)),
+        Err(_) => Ok(None),
+    }
+}
+
+impl SessionSettings {
+    pub fn from_env() -> Result<Self> {
+        let mut settings = SessionSettings::default();
+        if let Some(secs) = env_u64(IDLE_TIMEOUT_ENV)? {
+            settings.idle_timeout = (secs > 0).then(|| Duration::from_secs(secs));
+        }
+        if let Some(max) = env_u64(MAX_SESSIONS_ENV)? {
+            settings.max_sessions = (max > 0).then_some(max as usize);
+        }
+        if let Some(secs) = env_u64(SSE_KEEP_ALIVE_ENV)? {
+            settings.sse_keep_alive = (secs > 0).then(|| Duration::from_secs(secs));
+        }
+        if let Ok(value) = std::env::var(STATELESS_ENV) {
+            settings.stateless = matches!(value.trim(), "1" | "true" | "yes");
+        }
+        Ok(settings)
+    }
+
+    pub fn server_config(&self) -> StreamableHttpServerConfig {
+        StreamableHttpServerConfig {
+            sse_keep_alive: self.sse_keep_alive,
+            stateful_mode: !self.stateless,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SessionManagerError {
+    Local(LocalSessionManagerError),
+    TooManySessions(usize),
+}
+
+impl fmt::Display for SessionManagerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionManagerError::Local(e) => e.fmt(f),
+            SessionManagerError::TooManySessions(max) => {
+                write!(f, "too many sessions, at most {} are allowed", max)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SessionManagerError {}
+
+impl From<LocalSessionManagerError> for SessionManagerError {
+    fn from(e: LocalSessionManagerError) -> Self {
+        SessionManagerError::Local(e)
+    }
+}
+
+struct SessionActivity {
+    created_at: SystemTime,
+    last_active: Instant,
+    messages: u64,
+}
+
+// Session ids are bearer credentials, the view only shows a hash of them.
+#[derive(Serialize, Debug)]
+pub struct SessionInfo {
+    pub id_hash: String,
+    pub created_at: u64,
+    pub idle_secs: u64,
+    pub messages: u64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SessionsView {
+    pub count: usize,
+    pub max_sessions: Option<usize>,
+    pub idle_timeout_secs: Option<u64>,
+    pub sessions: Vec<SessionInfo>,
+}
+
+// Wraps the local session manager to cap the number of sessions and close idle ones,
+// the local manager alone never forgets a session its client walked away from.
+pub struct TrackedSessionManager {
+    inner: LocalSessionManager,
+    activity: Mutex<HashMap<SessionId, SessionActivity>>,
+    // Live sessions plus the ones being created, a slot is reserved before the local
+    // manager is asked for a session so concurrent initializes cannot pass the cap.
+    slots: AtomicUsize,
+    settings: SessionSettings,
+}
+
+// Gives a reserved slot back unless the session it was taken for got created.
+struct SlotReservation<'a> {
+    slots: Option<&'a AtomicUsize>,
+}
+
+impl SlotReservation<'_> {
+    fn keep(mut self) {
+        self.slots = None;
+    }
+}
+
+impl Drop for SlotReservation<'_> {
+    fn drop(&mut self) {
+        if let Some(slots) = self.slots {
+            slots.fetch_sub(1, Ordering::AcqRel);
+        }
+    }
+}
+
+// Session ids are bearer credentials, this short hash is what admin views and logs show.
+pub fn hash_session_id(id: &SessionId) -> String {
+    keccak256(id.as_bytes())[..8]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+impl TrackedSessionManager {
+    pub fn new(settings: SessionSettings) -> Self {
+        TrackedSessionManager {
+            inner: LocalSessionManager::default(),
+            activity: Mutex::new(HashMap::new()),
+            slots: AtomicUsize::new(0),
+            settings,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.activity.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        match self.settings.max_sessions {
+            Some(max) => self.slots.load(Ordering::Acquire) >= max,
+            None => false,
+        }
+    }
+
+    fn reserve_slot(&self) -> Result<SlotReservation<'_>, SessionManagerError> {
+        let max = self.settings.max_sessions.unwrap_or(usize::MAX);
+        self.slots
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < max).then_some(n + 1)
+            })
+            .map_err(|_| SessionManagerError::TooManySessions(max))?;
+        Ok(SlotReservation {
+            slots: Some(&self.slots),
+        })
+    }
+
+    pub fn session_ids(&self) -> Vec<SessionId> {
+        self.activity.lock().unwrap().keys().cloned().collect()
+    }
+
+    fn touch(&self, id: &SessionId) {
+        if let Some(activity) = self.activity.lock().unwrap().get_mut(id) {
+            activity.last_active = Instant::now();
+            activity.messages += 1;
+        }
+    }
+
+    pub fn view(&self) -> SessionsView {
+        let activity = self.activity.lock().unwrap();
+        let mut sessions: Vec<SessionInfo> = activity
+            .iter()
+            .map(|(id, activity)| SessionInfo {
+                id_hash: hash_session_id(id),
+                created_at: activity
+                    .created_at
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs(),
+                idle_secs: activity.last_active.elapsed().as_secs(),
+                messages: activity.messages,
+            })
+            .collect();
+        sessions.sort_by_key(|session| session.created_at);
+        SessionsView {
+            count: sessions.len(),
+            max_sessions: self.settings.max_sessions,
+            idle_timeout_secs: self.settings.idle_timeout.map(|d| d.as_secs()),
+            sessions,
+        }
+    }
+
+    pub async fn close_idle_sessions(&self) -> usize {
+        let Some(idle_timeout) = self.settings.idle_timeout else {
+            return 0;
+        };
+        let idle: Vec<SessionId> = self
+            .activity
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, activity)| activity.last_active.elapsed() >= idle_timeout)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &idle {
+            tracing::info!("closing idle session {}", hash_session_id(id));
+            if let Err(e) = self.close_session(id).await {
+                tracing::warn!("failed to close session {}: {}", hash_session_id(id), e);
+            }
+        }
+        idle.len()
+    }
+
+    pub async fn run_reaper(self: Arc<Self>) {
+        let Some(idle_timeout) = self.settings.idle_timeout else {
+            return;
+        };
+        let mut interval = tokio::time::interval((idle_timeout / 4).max(Duration::from_secs(1)));
+        loop {
+            interval.tick().await;
+            self.close_idle_sessions().await;
+        }
+    }
+}
+
+impl SessionManager for TrackedSessionManager {
+    type Error = SessionManagerError;
+    type Transport = <LocalSessionManager as SessionManager>::Transport;
+
+    async fn create_session(&self) -> Result<(SessionId, Self::Transport), Self::Error> {
+        let reservation = self.reserve_slot()?;
+        let (id, transport) = self.inner.create_session().await?;
+        reservation.keep();
+        self.activity.lock().unwrap().insert(
+            id.clone(),
+            SessionActivity {
+                created_at: SystemTime::now(),
+                last_active: Instant::now(),
+                messages: 0,
+            },
+        );
+        Ok((id, transport))
+    }
+
+    async fn initialize_session(
+        &self,
+        id: &SessionId,
+        message: ClientJsonRpcMessage,
+    ) -> Result<ServerJsonRpcMessage, Self::Error> {
+        self.touch(id);
+        Ok(self.inner.initialize_session(id, message).await?)
+    }
+
+    async fn has_session(&self, id: &SessionId) -> Result<bool, Self::Error> {
+        Ok(self.inner.has_session(id).await?)
+    }
+
+    async fn close_session(&self, id: &SessionId) -> Result<(), Self::Error> {
+        if self.activity.lock().unwrap().remove(id).is_some() {
+            self.slots.fetch_sub(1, Ordering::AcqRel);
+        }
+        Ok(self.inner.close_session(id).await?)
+    }
+
+    async fn create_stream(
+        &self,
+        id: &SessionId,
+        message: ClientJsonRpcMessage,
+    ) -> Result<impl Stream<Item = ServerSseMessage> + Send + Sync + 'static, Self::Error> {
+        self.touch(id);
+        Ok(self.inner.create_stream(id, message).await?)
+    }
+
+    async fn accept_message(
+        &self,
+        id: &SessionId,
+        message: ClientJsonRpcMessage,
+    ) -> Result<(), Self::Error> {
+        self.touch(id);
+        Ok(self.inner.accept_message(id, message).await?)
+    }
+
+    async fn create_standalone_stream(
+        &self,
+        id: &SessionId,
+    ) -> Result<impl Stream<Item = ServerSseMessage> + Send + Sync + 'static, Self::Error> {
+        self.touch(id);
+        Ok(self.inner.create_standalone_stream(id).await?)
+    }
+
+    async fn resume(
+        &self,
+        id: &SessionId,
+        last_event_id: String,
+    ) -> Result<impl Stream<Item = ServerSseMessage> + Send + Sync + 'static, Self::Error> {
+        self.touch(id);
+        Ok(self.inner.resume(id, last_event_id).await?)
+    }
+}
+
+// Turns away new sessions with a 503 once the cap is reached, rather than a bare 500.
+pub async fn limit_sessions(
+    State(session_manager): State<Arc<TrackedSessionManager>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if request.method() == Method::POST
+        && !request.headers().contains_key("mcp-session-id")
+        && session_manager.is_full()
+    {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "too many sessions, try again later",
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
+
+pub async fn list_sessions(
+    State(session_manager): State<Arc<TrackedSessionManager>>,
+) -> Json<SessionsView> {
+    Json(session_manager.view())
+}
+
+#[tokio::test]
+async fn test_tracked_session_manager() {
+    let manager = TrackedSessionManager::new(SessionSettings {
+        idle_timeout: Some(Duration::from_millis(20)),
+        max_sessions: Some(1),
+        ..Default::default()
+    });
+    let (id, _transport) = manager.create_session().await.unwrap();
+    assert!(manager.is_full());
+    assert!(matches!(
+        manager.create_session().await,
+        Err(SessionManagerError::TooManySessions(1))
+    ));
+    assert_eq!(manager.view().sessions[0].id_hash, hash_session_id(&id));
+    assert!(
+        !serde_json::to_string(&manager.view())
+            .unwrap()
+            .contains(&*id)
+    );
+
+    assert_eq!(manager.close_idle_sessions().await, 0);
+    tokio::time::sleep(Duration::from_millis(30)).await;
+    assert_eq!(manager.close_idle_sessions().await, 1);
+    assert!(manager.is_empty());
+    assert!(!manager.has_session(&id).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_session_cap_under_concurrency() {
+    let manager = TrackedSessionManager::new(SessionSettings {
+        max_sessions: Some(2),
+        ..Default::default()
+    });
+    let created = futures::future::join_all((0..16).map(|_| manager.create_session())).await;
+    let ids: Vec<SessionId> = created
+        .into_iter()
+        .filter_map(|res| res.ok().map(|(id, _)| id))
+        .collect();
+    assert_eq!(ids.len(), 2);
+    assert!(manager.is_full());
+
+    manager.close_session(&ids[0]).await.unwrap();
+    manager.close_session(&ids[0]).await.unwrap();
+    assert!(!manager.is_full());
+    manager.create_session().await.unwrap();
+    assert!(manager.create_session().await.is_err());
+}
@@ -0,0 +1,149 @@
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+pub const GRACE_PERIOD_ENV: &str = "SHUTDOWN_GRACE_PERIOD_SECS";
+
+const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(30);
+
+// Tracks in-flight tool calls so shutdown can wait for them before exiting.
+#[derive(Clone)]
+pub struct Drain {
+    draining: CancellationToken,
+    cancel: CancellationToken,
+    in_flight: Arc<watch::Sender<usize>>,
+}
+
+pub struct InFlightGuard {
+    in_flight: Arc<watch::Sender<usize>>,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.in_flight.send_modify(|n| *n -= 1);
+    }
+}
+
+impl Default for Drain {
+    fn default() -> Self {
+        Drain {
+            draining: CancellationToken::new(),
+            cancel: CancellationToken::new(),
+            in_flight: Arc::new(watch::Sender::new(0)),
+        }
+    }
+}
+
+impl Drain {
+    pub fn grace_period_from_env() -> Duration {
+        std::env::var(GRACE_PERIOD_ENV)
+            .ok()
+            .and_then(|secs| secs.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_GRACE_PERIOD)
+    }
+
+    pub fn enter(&self) -> InFlightGuard {
+        self.in_flight.send_modify(|n| *n += 1);
+        InFlightGuard {
+            in_flight: self.in_flight.clone(),
+        }
+    }
+
+    pub fn in_flight(&self) -> usize {
+        *self.in_flight.borrow()
+    }
+
+    pub fn start(&self) {
+        self.draining.cancel();
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.is_cancelled()
+    }
+
+    pub async fn started(self) {
+        self.draining.cancelled().await
+    }
+
+    // Returns false if calls were still running when the grace period ran out.
+    pub async fn wait_idle(&self, grace_period: Duration) -> bool {
+        let mut in_flight = self.in_flight.subscribe();
+        tokio::time::timeout(grace_period, in_flight.wait_for(|n| *n == 0))
+            .await
+            .is_ok()
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    pub async fn cancelled(&self) {
+        self.cancel.cancelled().await
+    }
+}
+
+pub async fn signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("failed to listen for SIGINT: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("received SIGINT"),
+        _ = terminate => tracing::info!("received SIGTERM"),
+    }
+}
+
+// Existing sessions may keep working while draining, only new ones are turned away.
+pub async fn reject_new_sessions(
+    State(drain): State<Drain>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if drain.is_draining() && !request.headers().contains_key("mcp-session-id") {
+        return (StatusCode::SERVICE_UNAVAILABLE, "server is shutting down").into_response();
+    }
+    next.run(request).await
+}
+
+#[tokio::test]
+async fn test_drain() {
+    let drain = Drain::default();
+    let guard = drain.enter();
+    assert_eq!(drain.in_flight(), 1);
+    assert!(!drain.wait_idle(Duration::from_millis(10)).await);
+
+    drain.start();
+    assert!(drain.is_draining());
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        drop(guard);
+    });
+    assert!(drain.wait_idle(Duration::from_secs(5)).await);
+    assert_eq!(drain.in_flight(), 0);
+}
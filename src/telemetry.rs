@@ -0,0 +1,105 @@
+use anyhow::Result;
+use axum::http::HeaderMap;
+use opentelemetry::{global, trace::TracerProvider as _};
+use opentelemetry_http::HeaderInjector;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{Resource, propagation::TraceContextPropagator, trace::SdkTracerProvider};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+pub const OTLP_ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+pub const SERVICE_NAME_ENV: &str = "OTEL_SERVICE_NAME";
+
+const DEFAULT_SERVICE_NAME: &str = "blocks_mcp";
+
+// `endpoint` is the collector base url, spans are posted to `{endpoint}/v1/traces`.
+pub fn init_tracer_provider(endpoint: &str, service_name: &str) -> Result<SdkTracerProvider> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+        .build()?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(service_name.to_string())
+                .build(),
+        )
+        .build();
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    Ok(provider)
+}
+
+// Returns the tracing layer exporting spans over OTLP, or None when no collector is configured.
+pub fn layer_from_env<S>() -> Result<
+    Option<(
+        tracing_opentelemetry::OpenTelemetryLayer<S, opentelemetry_sdk::trace::Tracer>,
+        SdkTracerProvider,
+    )>,
+>
+where
+    S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+{
+    let endpoint = match std::env::var(OTLP_ENDPOINT_ENV) {
+        Ok(endpoint) if !endpoint.is_empty() => endpoint,
+        _ => return Ok(None),
+    };
+    let service_name =
+        std::env::var(SERVICE_NAME_ENV).unwrap_or_else(|_| DEFAULT_SERVICE_NAME.into());
+    let provider = init_tracer_provider(&endpoint, &service_name)?;
+    let tracer = provider.tracer(DEFAULT_SERVICE_NAME);
+    Ok(Some((
+        tracing_opentelemetry::layer().with_tracer(tracer),
+        provider,
+    )))
+}
+
+// Adds the W3C `traceparent` of the current span so upstream requests join the trace.
+pub fn inject_trace_context(headers: &mut HeaderMap) {
+    let context = tracing::Span::current().context();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut HeaderInjector(headers))
+    });
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_export_to_collector() {
+    use axum::{Router, body::Bytes, routing::post};
+    use tokio::sync::mpsc;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<Bytes>();
+    let collector = Router::new().route(
+        "/v1/traces",
+        post(move |body: Bytes| async move {
+            tx.send(body).unwrap();
+            ""
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, collector).await });
+
+    let provider = init_tracer_provider(&endpoint, "blocks_mcp_test").unwrap();
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("blocks_mcp_test")));
+    let mut headers = HeaderMap::new();
+    tracing::subscriber::with_default(subscriber, || {
+        let span = tracing::info_span!("tool_call", tool = "get_chain_stats");
+        let _enter = span.enter();
+        inject_trace_context(&mut headers);
+    });
+    let traceparent = headers.get("traceparent").unwrap().to_str().unwrap();
+    assert!(traceparent.starts_with("00-"));
+
+    tokio::task::spawn_blocking(move || provider.force_flush().unwrap())
+        .await
+        .unwrap();
+    let body = rx.recv().await.unwrap();
+    let trace_id = traceparent.split('-').nth(1).unwrap();
+    let trace_id: Vec<u8> = (0..trace_id.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&trace_id[i..i + 2], 16).unwrap())
+        .collect();
+    assert!(body.windows(trace_id.len()).any(|w| w == trace_id));
+    assert!(body.windows(9).any(|w| w == b"tool_call"));
+}
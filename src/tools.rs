@@ -4,13 +4,30 @@ use crate::block_scout_api::{
     GetBlocksParams, GetTokensParams, GetTransactionTokenTransfersParams, GetTransactionsParams,
     SearchParams,
 };
+use crate::render::{Graph, GraphFormat};
+use crate::types::{Address, AddressOrName, BlockId, TxHash};
+use crate::{
+    approvals, call_tree, completions, events, fund_flow, metrics::METRICS, multichain, portfolio,
+    prompts, shutdown::Drain,
+};
+use futures::StreamExt;
 use rmcp::{
-    ErrorData as McpError, ServerHandler,
-    handler::server::{router::tool::ToolRouter, tool::Parameters},
+    ErrorData as McpError, RoleServer, ServerHandler,
+    handler::server::{
+        router::tool::ToolRouter,
+        tool::{Parameters, ToolCallContext},
+    },
     model::*,
-    schemars, tool, tool_handler, tool_router,
+    schemars,
+    service::RequestContext,
+    tool, tool_router,
 };
-use serde_json::{Map, Value};
+use serde_json::{Map, Value, json};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::time::{Duration, Instant};
+use tracing::Instrument;
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct BaseRequest {
@@ -25,7 +42,9 @@ pub struct EmptyRequest {}
 pub struct SearchRequest {
     #[schemars(description = "the chain id to query")]
     pub chain_id: i32,
-    #[schemars(description = "the query to search, it can be token name, token symbol, address, transaction hash, block number, block hash")]
+    #[schemars(
+        description = "the query to search, it can be token name, token symbol, address, transaction hash, block number, block hash"
+    )]
     pub q: String,
 }
 
@@ -34,7 +53,7 @@ pub struct TransactionRequest {
     #[schemars(description = "the chain id to query")]
     pub chain_id: i32,
     #[schemars(description = "the transaction hash to query")]
-    pub transaction_hash: String,
+    pub transaction_hash: TxHash,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
@@ -42,51 +61,270 @@ pub struct BlockRequest {
     #[schemars(description = "the chain id to query")]
     pub chain_id: i32,
     #[schemars(description = "the block number or block hash to query")]
-    pub number_or_hash: String,
+    pub number_or_hash: BlockId,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct TransactionsRequest {
+    #[schemars(description = "the chain id to query")]
+    pub chain_id: i32,
+    #[schemars(description = "the transaction hashes to query, at most 25")]
+    pub transaction_hashes: Vec<TxHash>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct TransactionGraphRequest {
+    #[schemars(description = "the chain id to query")]
+    pub chain_id: i32,
+    #[schemars(description = "the transaction hash to query")]
+    pub transaction_hash: TxHash,
+    #[schemars(description = "json by default, or dot or mermaid for a diagram")]
+    #[serde(default)]
+    pub format: GraphFormat,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct AddressRequest {
     #[schemars(description = "the chain id to query")]
     pub chain_id: i32,
-    #[schemars(description = "the address hash to query")]
-    pub address_hash: String,
+    #[schemars(description = "the address hash or name (like vitalik.eth) to query")]
+    pub address_hash: AddressOrName,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct AddressGraphRequest {
+    #[schemars(description = "the chain id to query")]
+    pub chain_id: i32,
+    #[schemars(description = "the address hash or name (like vitalik.eth) to query")]
+    pub address_hash: AddressOrName,
+    #[schemars(description = "json by default, or dot or mermaid for a diagram")]
+    #[serde(default)]
+    pub format: GraphFormat,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct AddressesRequest {
+    #[schemars(description = "the chain id to query")]
+    pub chain_id: i32,
+    #[schemars(description = "the address hashes or names to query, at most 25")]
+    pub address_hashes: Vec<AddressOrName>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct MultiChainAddressRequest {
+    #[schemars(description = "the address hash or name (like vitalik.eth) to query")]
+    pub address_hash: AddressOrName,
+    #[schemars(description = "the chain ids to query, at most 50, defaults to every known chain")]
+    #[serde(default)]
+    pub chain_ids: Option<Vec<i32>>,
+    #[schemars(description = "whether to include testnets when chain_ids is not given")]
+    #[serde(default)]
+    pub include_testnets: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct FundFlowRequest {
+    #[schemars(description = "the chain id to query")]
+    pub chain_id: i32,
+    #[schemars(description = "the address hash or name to trace funds from")]
+    pub address_hash: AddressOrName,
+    #[schemars(description = "start from the transfers of this transaction only")]
+    #[serde(default)]
+    pub transaction_hash: Option<TxHash>,
+    #[schemars(description = "only follow transfers of this token")]
+    #[serde(default)]
+    pub token_address: Option<AddressOrName>,
+    #[schemars(description = "how many hops to follow, 3 by default and at most 5")]
+    #[serde(default)]
+    pub max_hops: Option<usize>,
+    #[schemars(
+        description = "skip transfers worth less than this many USD at the explorer's exchange rate, above zero transfers without a price are skipped too"
+    )]
+    #[serde(default)]
+    pub min_value: Option<f64>,
+    #[schemars(
+        description = "how many of the largest transfers to follow per address, by USD value where priced, 5 by default and at most 20"
+    )]
+    #[serde(default)]
+    pub max_fan_out: Option<usize>,
+    #[schemars(description = "how many explorer requests to spend, 30 by default and at most 100")]
+    #[serde(default)]
+    pub max_requests: Option<usize>,
+    #[schemars(description = "json by default, or dot or mermaid for a diagram")]
+    #[serde(default)]
+    pub format: GraphFormat,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct TokenRequest {
     #[schemars(description = "the chain id to query")]
     pub chain_id: i32,
-    #[schemars(description = "the token address to query")]
-    pub token_address: String,
+    #[schemars(description = "the token address or name to query")]
+    pub token_address: AddressOrName,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct TokenInstanceRequest {
     #[schemars(description = "the chain id to query")]
     pub chain_id: i32,
-    #[schemars(description = "the token address to query")]
-    pub token_address: String,
+    #[schemars(description = "the token address or name to query")]
+    pub token_address: AddressOrName,
     #[schemars(description = "the token id to query")]
     pub token_id: u64,
 }
 
+#[derive(Debug, serde::Serialize)]
+pub struct Resolution {
+    pub name: String,
+    pub address: Address,
+}
+
+const MAX_NAME_LOOKUPS: usize = 10;
+const NAME_LOOKUP_TIMEOUT: Duration = Duration::from_secs(2);
+
+const MAX_BATCH_SIZE: usize = 25;
+const MAX_CONCURRENT_LOOKUPS: usize = 8;
+
+fn check_batch<T: PartialEq>(mut items: Vec<T>) -> Result<Vec<T>, McpError> {
+    let mut unique = Vec::with_capacity(items.len());
+    for item in items.drain(..) {
+        if !unique.contains(&item) {
+            unique.push(item);
+        }
+    }
+    match unique.len() {
+        0 => Err(McpError::invalid_params("the batch is empty", None)),
+        len if len > MAX_BATCH_SIZE => Err(McpError::invalid_params(
+            format!(
+                "the batch has {} items, at most {} are allowed",
+                len, MAX_BATCH_SIZE
+            ),
+            None,
+        )),
+        _ => Ok(unique),
+    }
+}
+
+// Runs a lookup per item concurrently, keyed by the item in normalized form, i.e.
+// checksummed addresses, lowercase hashes and lowercase names. A failing item lands
+// in `errors` and leaves the others alone.
+async fn batch<T, F, Fut>(items: Vec<T>, lookup: F) -> Value
+where
+    T: fmt::Display,
+    F: Fn(T) -> Fut,
+    Fut: Future<Output = Result<Value, String>>,
+{
+    let lookup = &lookup;
+    let outcomes: Vec<(String, Result<Value, String>)> = futures::stream::iter(items)
+        .map(|item| async move { (item.to_string(), lookup(item).await) })
+        .buffer_unordered(MAX_CONCURRENT_LOOKUPS)
+        .collect()
+        .await;
+    let mut results = BTreeMap::new();
+    let mut errors = BTreeMap::new();
+    for (key, outcome) in outcomes {
+        match outcome {
+            Ok(value) => {
+                results.insert(key, value);
+            }
+            Err(e) => {
+                errors.insert(key, e);
+            }
+        }
+    }
+    json!({ "results": results, "errors": errors })
+}
+
+// Blockscout address objects carry `hash` and a nullable `ens_domain_name`.
+fn for_each_address(value: &mut Value, f: &mut impl FnMut(&mut Map<String, Value>)) {
+    match value {
+        Value::Object(object) => {
+            if object.contains_key("hash") && object.contains_key("ens_domain_name") {
+                f(object);
+            }
+            for value in object.values_mut() {
+                for_each_address(value, f);
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                for_each_address(value, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn unnamed_address(object: &Map<String, Value>) -> Option<Address> {
+    if !object.get("ens_domain_name")?.is_null() {
+        return None;
+    }
+    object.get("hash")?.as_str()?.parse().ok()
+}
+
+// What an input name resolved to goes under `resolved` in the result, so the caller
+// knows which address was queried. Results that are not objects get wrapped.
+fn with_resolved(data: Value, resolved: Option<Resolution>) -> Value {
+    let Some(resolved) = resolved else {
+        return data;
+    };
+    match data {
+        Value::Object(mut object) => {
+            object.insert("resolved".into(), json!(resolved));
+            Value::Object(object)
+        }
+        data => json!({ "result": data, "resolved": resolved }),
+    }
+}
+
+fn session_id(context: &RequestContext<RoleServer>) -> Option<String> {
+    let parts = context.extensions.get::<axum::http::request::Parts>()?;
+    let session_id = parts.headers.get("mcp-session-id")?.to_str().ok()?;
+    Some(session_id.to_string())
+}
+
+// A short fingerprint so identical calls can be correlated without logging the arguments.
+fn arguments_hash(arguments: &Option<JsonObject>) -> String {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_string(arguments)
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
 #[derive(Clone)]
 pub struct OnChainData {
     block_scout_api: API,
     tool_router: ToolRouter<OnChainData>,
+    drain: Drain,
+}
+
+impl Default for OnChainData {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[tool_router]
 impl OnChainData {
     #[allow(dead_code)]
     pub fn new() -> Self {
+        Self::with_api(API::new())
+    }
+
+    pub fn with_api(block_scout_api: API) -> Self {
         Self {
-            block_scout_api: API::new(),
+            block_scout_api,
             tool_router: Self::tool_router(),
+            drain: Drain::default(),
         }
     }
 
+    pub fn with_drain(mut self, drain: Drain) -> Self {
+        self.drain = drain;
+        self
+    }
+
     fn convert_result(rst: anyhow::Result<Value>) -> Result<CallToolResult, McpError> {
         match rst {
             Ok(r) => Ok(CallToolResult::success(vec![Content::text(
@@ -100,6 +338,102 @@ impl OnChainData {
         }
     }
 
+    // The diagram in place of the json when one was asked for.
+    fn convert_graph(
+        rst: anyhow::Result<Value>,
+        format: GraphFormat,
+        graph: impl FnOnce(&Value) -> Graph,
+    ) -> Result<CallToolResult, McpError> {
+        match rst {
+            Ok(data) => match graph(&data).render(format) {
+                Some(diagram) => Ok(CallToolResult::success(vec![Content::text(diagram)])),
+                None => Self::convert_result(Ok(data)),
+            },
+            Err(e) => Self::convert_result(Err(e)),
+        }
+    }
+
+    async fn resolve(
+        &self,
+        chain_id: i32,
+        input: AddressOrName,
+    ) -> Result<(Address, Option<Resolution>), McpError> {
+        let name = match input {
+            AddressOrName::Address(address) => return Ok((address, None)),
+            AddressOrName::Name(name) => name,
+        };
+        match self.block_scout_api.resolve_name(chain_id, &name).await {
+            Ok(Some(address)) => Ok((address.clone(), Some(Resolution { name, address }))),
+            Ok(None) => Err(McpError::invalid_params(
+                format!(
+                    "{} does not resolve to an address on chain {}",
+                    name, chain_id
+                ),
+                None,
+            )),
+            Err(e) => Err(McpError::internal_error(
+                format!("failed to resolve {}: {}", name, e),
+                None,
+            )),
+        }
+    }
+
+    // Names the addresses in the output and echoes what an input name resolved to.
+    async fn convert_named(
+        &self,
+        chain_id: i32,
+        mut rst: anyhow::Result<Value>,
+        resolved: Option<Resolution>,
+    ) -> Result<CallToolResult, McpError> {
+        self.annotate_names(chain_id, &mut rst).await;
+        Self::convert_result(rst.map(|data| with_resolved(data, resolved)))
+    }
+
+    // Best effort, addresses the explorer returned without a name are looked up
+    // in the name service. Failed or slow lookups leave the output as it was.
+    async fn annotate_names(&self, chain_id: i32, rst: &mut anyhow::Result<Value>) {
+        let Ok(data) = rst else {
+            return;
+        };
+        let mut addresses = Vec::<Address>::new();
+        for_each_address(data, &mut |object| {
+            if let Some(address) = unnamed_address(object)
+                && !addresses.contains(&address)
+                && addresses.len() < MAX_NAME_LOOKUPS
+            {
+                addresses.push(address);
+            }
+        });
+        let lookups = addresses.iter().map(|address| async move {
+            let name = self
+                .block_scout_api
+                .lookup_address_name(chain_id, address)
+                .await;
+            (address.clone(), name)
+        });
+        let lookups = tokio::time::timeout(NAME_LOOKUP_TIMEOUT, futures::future::join_all(lookups))
+            .await
+            .unwrap_or_else(|_| {
+                tracing::debug!("name lookups timed out after {:?}", NAME_LOOKUP_TIMEOUT);
+                Vec::new()
+            });
+        let mut names = HashMap::new();
+        for (address, name) in lookups {
+            match name {
+                Ok(Some(name)) => {
+                    names.insert(address, name);
+                }
+                Ok(None) => {}
+                Err(e) => tracing::debug!("name lookup for {} failed: {}", address, e),
+            }
+        }
+        for_each_address(data, &mut |object| {
+            if let Some(name) = unnamed_address(object).and_then(|address| names.get(&address)) {
+                object.insert("ens_domain_name".into(), Value::String(name.clone()));
+            }
+        });
+    }
+
     #[tool(
         description = "Search chain data with token name, token symbol, account name, address, transaction hash"
     )]
@@ -141,7 +475,7 @@ impl OnChainData {
                 },
             )
             .await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, None).await
     }
 
     #[tool(description = "List latest 50 blocks")]
@@ -158,7 +492,7 @@ impl OnChainData {
                 },
             )
             .await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, None).await
     }
 
     #[tool(description = "List latest 50 token transfers")]
@@ -167,7 +501,7 @@ impl OnChainData {
         Parameters(BaseRequest { chain_id }): Parameters<BaseRequest>,
     ) -> Result<CallToolResult, McpError> {
         let rst = self.block_scout_api.get_transfers(chain_id).await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, None).await
     }
 
     #[tool(description = "List latest 50 internal transactions")]
@@ -179,7 +513,7 @@ impl OnChainData {
             .block_scout_api
             .get_internal_transactions(chain_id)
             .await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, None).await
     }
 
     #[tool(description = "List latest 50 withdrawals")]
@@ -188,7 +522,7 @@ impl OnChainData {
         Parameters(BaseRequest { chain_id }): Parameters<BaseRequest>,
     ) -> Result<CallToolResult, McpError> {
         let rst = self.block_scout_api.get_withdrawals(chain_id).await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, None).await
     }
 
     #[tool(description = "Get chain stats counters")]
@@ -212,7 +546,28 @@ impl OnChainData {
             .block_scout_api
             .get_transaction_info(chain_id, transaction_hash)
             .await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, None).await
+    }
+
+    #[tool(
+        description = "Get info of up to 25 transactions at once, keyed by hash with per-transaction errors"
+    )]
+    async fn get_transactions_info(
+        &self,
+        Parameters(TransactionsRequest {
+            chain_id,
+            transaction_hashes,
+        }): Parameters<TransactionsRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let transaction_hashes = check_batch(transaction_hashes)?;
+        let rst = batch(transaction_hashes, |transaction_hash| async move {
+            self.block_scout_api
+                .get_transaction_info(chain_id, transaction_hash)
+                .await
+                .map_err(|e| e.to_string())
+        })
+        .await;
+        self.convert_named(chain_id, Ok(rst), None).await
     }
 
     #[tool(description = "Get transaction token transfers")]
@@ -233,22 +588,44 @@ impl OnChainData {
                 },
             )
             .await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, None).await
     }
 
     #[tool(description = "Get transaction internal transactions")]
     async fn get_transaction_internal_transactions(
         &self,
-        Parameters(TransactionRequest {
+        Parameters(TransactionGraphRequest {
             chain_id,
             transaction_hash,
-        }): Parameters<TransactionRequest>,
+            format,
+        }): Parameters<TransactionGraphRequest>,
     ) -> Result<CallToolResult, McpError> {
-        let rst = self
+        let mut rst = self
             .block_scout_api
             .get_transaction_internal_transactions(chain_id, transaction_hash)
             .await;
-        Self::convert_result(rst)
+        self.annotate_names(chain_id, &mut rst).await;
+        Self::convert_graph(rst, format, Graph::from_internal_transactions)
+    }
+
+    #[tool(
+        description = "Get the nested call tree of a transaction with call types, values, gas, reverts and decoded methods"
+    )]
+    async fn get_transaction_call_tree(
+        &self,
+        Parameters(TransactionGraphRequest {
+            chain_id,
+            transaction_hash,
+            format,
+        }): Parameters<TransactionGraphRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let tree = call_tree::call_tree(&self.block_scout_api, chain_id, transaction_hash).await;
+        if let Ok(tree) = &tree
+            && let Some(diagram) = Graph::from_call_tree(tree).render(format)
+        {
+            return Ok(CallToolResult::success(vec![Content::text(diagram)]));
+        }
+        Self::convert_result(tree.and_then(|tree| serde_json::to_value(tree).map_err(Into::into)))
     }
 
     #[tool(description = "Get transaction logs")]
@@ -259,11 +636,14 @@ impl OnChainData {
             transaction_hash,
         }): Parameters<TransactionRequest>,
     ) -> Result<CallToolResult, McpError> {
-        let rst = self
+        let mut rst = self
             .block_scout_api
             .get_transaction_logs(chain_id, transaction_hash)
             .await;
-        Self::convert_result(rst)
+        if let Ok(logs) = &mut rst {
+            events::decode_logs(logs);
+        }
+        self.convert_named(chain_id, rst, None).await
     }
 
     #[tool(description = "Get transaction summary")]
@@ -278,7 +658,7 @@ impl OnChainData {
             .block_scout_api
             .get_transaction_summary(chain_id, transaction_hash)
             .await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, None).await
     }
 
     #[tool(description = "Get block info")]
@@ -293,7 +673,7 @@ impl OnChainData {
             .block_scout_api
             .get_block_info(chain_id, number_or_hash)
             .await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, None).await
     }
 
     #[tool(description = "Get block transactions")]
@@ -308,7 +688,7 @@ impl OnChainData {
             .block_scout_api
             .get_block_transactions(chain_id, number_or_hash)
             .await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, None).await
     }
 
     #[tool(description = "Get block withdrawals")]
@@ -323,7 +703,7 @@ impl OnChainData {
             .block_scout_api
             .get_block_withdrawals(chain_id, number_or_hash)
             .await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, None).await
     }
 
     #[tool(description = "List top 50 native coin holders")]
@@ -332,7 +712,7 @@ impl OnChainData {
         Parameters(BaseRequest { chain_id }): Parameters<BaseRequest>,
     ) -> Result<CallToolResult, McpError> {
         let rst = self.block_scout_api.get_addresses(chain_id).await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, None).await
     }
 
     #[tool(description = "Get address info")]
@@ -343,11 +723,82 @@ impl OnChainData {
             address_hash,
         }): Parameters<AddressRequest>,
     ) -> Result<CallToolResult, McpError> {
+        let (address_hash, resolved) = self.resolve(chain_id, address_hash).await?;
         let rst = self
             .block_scout_api
             .get_address_info(chain_id, address_hash)
             .await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, resolved).await
+    }
+
+    #[tool(
+        description = "Get info of up to 25 addresses or names at once, keyed by checksummed address or lowercase name with per-address errors"
+    )]
+    async fn get_addresses_info(
+        &self,
+        Parameters(AddressesRequest {
+            chain_id,
+            address_hashes,
+        }): Parameters<AddressesRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let address_hashes = check_batch(address_hashes)?;
+        let rst = batch(address_hashes, |address_hash| async move {
+            let (address_hash, resolved) = self
+                .resolve(chain_id, address_hash)
+                .await
+                .map_err(|e| e.message.to_string())?;
+            let info = self
+                .block_scout_api
+                .get_address_info(chain_id, address_hash)
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(with_resolved(info, resolved))
+        })
+        .await;
+        self.convert_named(chain_id, Ok(rst), None).await
+    }
+
+    #[tool(
+        description = "Trace where funds went from an address, following outgoing native and token transfers for several hops. Returns a graph of addresses and transfers"
+    )]
+    async fn trace_fund_flow(
+        &self,
+        Parameters(request): Parameters<FundFlowRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let chain_id = request.chain_id;
+        let (address_hash, resolved) = self.resolve(chain_id, request.address_hash).await?;
+        let token = match request.token_address {
+            Some(token) => Some(self.resolve(chain_id, token).await?.0),
+            None => None,
+        };
+        let defaults = fund_flow::TraceOptions::default();
+        let options = fund_flow::TraceOptions {
+            max_hops: request
+                .max_hops
+                .unwrap_or(defaults.max_hops)
+                .min(fund_flow::MAX_HOPS),
+            min_value: request.min_value.unwrap_or(defaults.min_value),
+            max_fan_out: request
+                .max_fan_out
+                .unwrap_or(defaults.max_fan_out)
+                .min(fund_flow::MAX_FAN_OUT),
+            max_requests: request
+                .max_requests
+                .unwrap_or(defaults.max_requests)
+                .min(fund_flow::MAX_REQUESTS),
+            transaction_hash: request.transaction_hash,
+            token,
+        };
+        let flow = fund_flow::trace(&self.block_scout_api, chain_id, address_hash, &options).await;
+        if let Some(diagram) = Graph::from_fund_flow(&flow).render(request.format) {
+            return Ok(CallToolResult::success(vec![Content::text(diagram)]));
+        }
+        self.convert_named(
+            chain_id,
+            serde_json::to_value(flow).map_err(Into::into),
+            resolved,
+        )
+        .await
     }
 
     #[tool(description = "Get address counters")]
@@ -358,11 +809,55 @@ impl OnChainData {
             address_hash,
         }): Parameters<AddressRequest>,
     ) -> Result<CallToolResult, McpError> {
+        let (address_hash, resolved) = self.resolve(chain_id, address_hash).await?;
         let rst = self
             .block_scout_api
             .get_address_counters(chain_id, address_hash)
             .await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, resolved).await
+    }
+
+    #[tool(
+        description = "Find the chains an address is active on, with its balance and counters on each"
+    )]
+    async fn find_address_chains(
+        &self,
+        Parameters(MultiChainAddressRequest {
+            address_hash,
+            chain_ids,
+            include_testnets,
+        }): Parameters<MultiChainAddressRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let chain_ids = match chain_ids {
+            Some(mut chain_ids) => {
+                chain_ids.sort();
+                chain_ids.dedup();
+                if chain_ids.len() > multichain::MAX_CHAINS {
+                    return Err(McpError::invalid_params(
+                        format!(
+                            "{} chains were given, at most {} are allowed",
+                            chain_ids.len(),
+                            multichain::MAX_CHAINS
+                        ),
+                        None,
+                    ));
+                }
+                chain_ids
+            }
+            None => {
+                multichain::candidate_chains(
+                    &self.block_scout_api,
+                    include_testnets.unwrap_or_default(),
+                )
+                .await
+            }
+        };
+        // Names are resolved on mainnet, where ENS lives.
+        let (address_hash, resolved) = self.resolve(1, address_hash).await?;
+        let activity =
+            multichain::address_activity(&self.block_scout_api, &address_hash, chain_ids).await;
+        let activity = serde_json::to_value(activity).map_err(Into::into);
+        self.convert_named(1, activity, resolved).await
     }
 
     #[tool(description = "List latest 50 transactions of the address")]
@@ -373,6 +868,7 @@ impl OnChainData {
             address_hash,
         }): Parameters<AddressRequest>,
     ) -> Result<CallToolResult, McpError> {
+        let (address_hash, resolved) = self.resolve(chain_id, address_hash).await?;
         let rst = self
             .block_scout_api
             .get_address_transactions(
@@ -381,18 +877,20 @@ impl OnChainData {
                 GetAddressTransactionsParams { filter: "".into() },
             )
             .await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, resolved).await
     }
 
     #[tool(description = "List latest 50 token transfers of the address")]
     async fn get_address_token_transfers(
         &self,
-        Parameters(AddressRequest {
+        Parameters(AddressGraphRequest {
             chain_id,
             address_hash,
-        }): Parameters<AddressRequest>,
+            format,
+        }): Parameters<AddressGraphRequest>,
     ) -> Result<CallToolResult, McpError> {
-        let rst = self
+        let (address_hash, resolved) = self.resolve(chain_id, address_hash).await?;
+        let mut rst = self
             .block_scout_api
             .get_address_token_transfers(
                 chain_id,
@@ -402,7 +900,12 @@ impl OnChainData {
                 },
             )
             .await;
-        Self::convert_result(rst)
+        self.annotate_names(chain_id, &mut rst).await;
+        Self::convert_graph(
+            rst.map(|data| with_resolved(data, resolved)),
+            format,
+            Graph::from_token_transfers,
+        )
     }
 
     #[tool(description = "List latest 50 internal transactions of the address")]
@@ -413,6 +916,7 @@ impl OnChainData {
             address_hash,
         }): Parameters<AddressRequest>,
     ) -> Result<CallToolResult, McpError> {
+        let (address_hash, resolved) = self.resolve(chain_id, address_hash).await?;
         let rst = self
             .block_scout_api
             .get_address_internal_transactions(
@@ -423,7 +927,7 @@ impl OnChainData {
                 },
             )
             .await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, resolved).await
     }
 
     #[tool(description = "Get address tokens")]
@@ -434,6 +938,7 @@ impl OnChainData {
             address_hash,
         }): Parameters<AddressRequest>,
     ) -> Result<CallToolResult, McpError> {
+        let (address_hash, resolved) = self.resolve(chain_id, address_hash).await?;
         let rst = self
             .block_scout_api
             .get_address_tokens(
@@ -444,7 +949,66 @@ impl OnChainData {
                 },
             )
             .await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, resolved).await
+    }
+
+    #[tool(
+        description = "Value the wallet of an address in USD: its native balance and every token balance across all pages, converted with token decimals and the explorer's exchange rates, per asset and in total. Assets without an exchange rate or decimals are listed under `unpriced` and left out of the total"
+    )]
+    async fn get_address_portfolio(
+        &self,
+        Parameters(AddressRequest {
+            chain_id,
+            address_hash,
+        }): Parameters<AddressRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let (address_hash, resolved) = self.resolve(chain_id, address_hash).await?;
+        let portfolio = portfolio::portfolio(&self.block_scout_api, chain_id, address_hash).await;
+        self.convert_named(
+            chain_id,
+            portfolio.and_then(|portfolio| serde_json::to_value(portfolio).map_err(Into::into)),
+            resolved,
+        )
+        .await
+    }
+
+    #[tool(description = "List latest 50 logs emitted by the address")]
+    async fn get_address_logs(
+        &self,
+        Parameters(AddressRequest {
+            chain_id,
+            address_hash,
+        }): Parameters<AddressRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let (address_hash, resolved) = self.resolve(chain_id, address_hash).await?;
+        let mut rst = self
+            .block_scout_api
+            .get_address_logs(chain_id, address_hash)
+            .await;
+        if let Ok(logs) = &mut rst {
+            events::decode_logs(logs);
+        }
+        self.convert_named(chain_id, rst, resolved).await
+    }
+
+    #[tool(
+        description = "Audit the outstanding token approvals of an address: ERC-20 allowances and NFT operator approvals (setApprovalForAll) it granted and did not revoke, rebuilt from every Approval and ApprovalForAll event naming it as the owner, so approvals through routers, permits and contract wallets count. Unlimited approvals come first, spenders are labeled with their name and whether they are verified contracts. `truncated` is set when not every event was read"
+    )]
+    async fn get_token_approvals(
+        &self,
+        Parameters(AddressRequest {
+            chain_id,
+            address_hash,
+        }): Parameters<AddressRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let (address_hash, resolved) = self.resolve(chain_id, address_hash).await?;
+        let audit = approvals::audit(&self.block_scout_api, chain_id, address_hash).await;
+        self.convert_named(
+            chain_id,
+            audit.and_then(|audit| serde_json::to_value(audit).map_err(Into::into)),
+            resolved,
+        )
+        .await
     }
 
     #[tool(description = "Get address coin balance history")]
@@ -455,11 +1019,12 @@ impl OnChainData {
             address_hash,
         }): Parameters<AddressRequest>,
     ) -> Result<CallToolResult, McpError> {
+        let (address_hash, resolved) = self.resolve(chain_id, address_hash).await?;
         let rst = self
             .block_scout_api
             .get_address_coin_balance_history(chain_id, address_hash)
             .await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, resolved).await
     }
 
     #[tool(description = "Get address coin balance history by day")]
@@ -470,11 +1035,12 @@ impl OnChainData {
             address_hash,
         }): Parameters<AddressRequest>,
     ) -> Result<CallToolResult, McpError> {
+        let (address_hash, resolved) = self.resolve(chain_id, address_hash).await?;
         let rst = self
             .block_scout_api
             .get_address_coin_balance_history_by_day(chain_id, address_hash)
             .await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, resolved).await
     }
 
     #[tool(description = "Get address withdrawals")]
@@ -485,11 +1051,12 @@ impl OnChainData {
             address_hash,
         }): Parameters<AddressRequest>,
     ) -> Result<CallToolResult, McpError> {
+        let (address_hash, resolved) = self.resolve(chain_id, address_hash).await?;
         let rst = self
             .block_scout_api
             .get_address_withdrawals(chain_id, address_hash)
             .await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, resolved).await
     }
 
     #[tool(description = "Get address NFTs")]
@@ -500,6 +1067,7 @@ impl OnChainData {
             address_hash,
         }): Parameters<AddressRequest>,
     ) -> Result<CallToolResult, McpError> {
+        let (address_hash, resolved) = self.resolve(chain_id, address_hash).await?;
         let rst = self
             .block_scout_api
             .get_address_nfts(
@@ -510,7 +1078,7 @@ impl OnChainData {
                 },
             )
             .await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, resolved).await
     }
 
     #[tool(description = "Get address NFT collections")]
@@ -521,6 +1089,7 @@ impl OnChainData {
             address_hash,
         }): Parameters<AddressRequest>,
     ) -> Result<CallToolResult, McpError> {
+        let (address_hash, resolved) = self.resolve(chain_id, address_hash).await?;
         let rst = self
             .block_scout_api
             .get_address_nft_collections(
@@ -531,7 +1100,7 @@ impl OnChainData {
                 },
             )
             .await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, resolved).await
     }
 
     #[tool(description = "List top 50 tokens with the most holders")]
@@ -548,7 +1117,7 @@ impl OnChainData {
                 },
             )
             .await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, None).await
     }
 
     #[tool(description = "Get token info")]
@@ -559,11 +1128,12 @@ impl OnChainData {
             token_address,
         }): Parameters<TokenRequest>,
     ) -> Result<CallToolResult, McpError> {
+        let (token_address, resolved) = self.resolve(chain_id, token_address).await?;
         let rst = self
             .block_scout_api
             .get_token_info(chain_id, token_address)
             .await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, resolved).await
     }
 
     #[tool(description = "List latest 50 transfers of the token")]
@@ -574,11 +1144,12 @@ impl OnChainData {
             token_address,
         }): Parameters<TokenRequest>,
     ) -> Result<CallToolResult, McpError> {
+        let (token_address, resolved) = self.resolve(chain_id, token_address).await?;
         let rst = self
             .block_scout_api
             .get_token_transfers(chain_id, token_address)
             .await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, resolved).await
     }
 
     #[tool(description = "List top 50 holders of the token")]
@@ -589,11 +1160,12 @@ impl OnChainData {
             token_address,
         }): Parameters<TokenRequest>,
     ) -> Result<CallToolResult, McpError> {
+        let (token_address, resolved) = self.resolve(chain_id, token_address).await?;
         let rst = self
             .block_scout_api
             .get_token_holders(chain_id, token_address)
             .await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, resolved).await
     }
 
     #[tool(description = "Get token counters")]
@@ -604,11 +1176,12 @@ impl OnChainData {
             token_address,
         }): Parameters<TokenRequest>,
     ) -> Result<CallToolResult, McpError> {
+        let (token_address, resolved) = self.resolve(chain_id, token_address).await?;
         let rst = self
             .block_scout_api
             .get_token_counters(chain_id, token_address)
             .await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, resolved).await
     }
 
     #[tool(description = "List first 50 instances of the NFT")]
@@ -619,11 +1192,12 @@ impl OnChainData {
             token_address,
         }): Parameters<TokenRequest>,
     ) -> Result<CallToolResult, McpError> {
+        let (token_address, resolved) = self.resolve(chain_id, token_address).await?;
         let rst = self
             .block_scout_api
             .get_token_instances(chain_id, token_address)
             .await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, resolved).await
     }
 
     #[tool(description = "Get NFT instance info")]
@@ -635,11 +1209,12 @@ impl OnChainData {
             token_id,
         }): Parameters<TokenInstanceRequest>,
     ) -> Result<CallToolResult, McpError> {
+        let (token_address, resolved) = self.resolve(chain_id, token_address).await?;
         let rst = self
             .block_scout_api
             .get_token_instance_info(chain_id, token_address, token_id)
             .await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, resolved).await
     }
 
     #[tool(description = "List latest 50 transfers of the NFT instance")]
@@ -651,11 +1226,12 @@ impl OnChainData {
             token_id,
         }): Parameters<TokenInstanceRequest>,
     ) -> Result<CallToolResult, McpError> {
+        let (token_address, resolved) = self.resolve(chain_id, token_address).await?;
         let rst = self
             .block_scout_api
             .get_token_instance_transfers(chain_id, token_address, token_id)
             .await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, resolved).await
     }
 
     #[tool(description = "List fist 50 holders of the NFT instance")]
@@ -667,11 +1243,12 @@ impl OnChainData {
             token_id,
         }): Parameters<TokenInstanceRequest>,
     ) -> Result<CallToolResult, McpError> {
+        let (token_address, resolved) = self.resolve(chain_id, token_address).await?;
         let rst = self
             .block_scout_api
             .get_token_instance_holders(chain_id, token_address, token_id)
             .await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, resolved).await
     }
 
     #[tool(description = "Get the NFT instance transfers count")]
@@ -683,24 +1260,549 @@ impl OnChainData {
             token_id,
         }): Parameters<TokenInstanceRequest>,
     ) -> Result<CallToolResult, McpError> {
+        let (token_address, resolved) = self.resolve(chain_id, token_address).await?;
         let rst = self
             .block_scout_api
             .get_token_instance_transfers_count(chain_id, token_address, token_id)
             .await;
-        Self::convert_result(rst)
+        self.convert_named(chain_id, rst, resolved).await
     }
 }
 
-#[tool_handler]
 impl ServerHandler for OnChainData {
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
             protocol_version: ProtocolVersion::V_2024_11_05,
-            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            capabilities: ServerCapabilities::builder()
+                .enable_completions()
+                .enable_prompts()
+                .enable_tools()
+                .build(),
             server_info: Implementation::from_build_env(),
             instructions: Some(
                 "This server provides a tool for query blockchains on-chain data".to_string(),
             ),
         }
     }
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        let tool = request.name.to_string();
+        let span = tracing::info_span!(
+            "tool_call",
+            tool = %tool,
+            chain_id = tracing::field::Empty,
+            session_id = tracing::field::Empty,
+            arguments_hash = %arguments_hash(&request.arguments),
+            success = tracing::field::Empty,
+            duration_ms = tracing::field::Empty,
+        );
+        if let Some(chain_id) = request
+            .arguments
+            .as_ref()
+            .and_then(|arguments| arguments.get("chain_id"))
+            .and_then(Value::as_i64)
+        {
+            span.record("chain_id", chain_id);
+        }
+        if let Some(session_id) = session_id(&context) {
+            span.record("session_id", session_id);
+        }
+        async {
+            let _guard = self.drain.enter();
+            let start = Instant::now();
+            let rst = tokio::select! {
+                rst = self
+                    .tool_router
+                    .call(ToolCallContext::new(self, request, context)) => rst,
+                _ = self.drain.cancelled() => Err(McpError::internal_error(
+                    "server is shutting down, the tool call was cancelled",
+                    None,
+                )),
+            };
+            let success = matches!(&rst, Ok(r) if r.is_error != Some(true));
+            // Unknown names come from the client, keep them out of the metric labels.
+            let label = if self.tool_router.has_route(&tool) {
+                tool.as_str()
+            } else {
+                "unknown"
+            };
+            METRICS.observe_tool_call(label, success, start.elapsed());
+            let span = tracing::Span::current();
+            span.record("success", success);
+            span.record("duration_ms", start.elapsed().as_millis() as u64);
+            match &rst {
+                Err(e) => tracing::warn!("tool call failed: {}", e.message),
+                Ok(_) => tracing::info!("tool call finished"),
+            }
+            rst
+        }
+        .instrument(span)
+        .await
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        Ok(ListToolsResult::with_all_items(self.tool_router.list_all()))
+    }
+
+    async fn list_prompts(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListPromptsResult, McpError> {
+        Ok(ListPromptsResult::with_all_items(prompts::list()))
+    }
+
+    async fn get_prompt(
+        &self,
+        request: GetPromptRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<GetPromptResult, McpError> {
+        prompts::get(&request.name, &request.arguments)
+    }
+    async fn complete(
+        &self,
+        request: CompleteRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<CompleteResult, McpError> {
+        completions::complete(&self.block_scout_api, request).await
+    }
+}
+
+#[tokio::test]
+async fn test_tools() {
+    let mock = crate::mock_blockscout::MockBlockscout::start()
+        .await
+        .unwrap();
+    let data = OnChainData::with_api(mock.api());
+    let text = |rst: Result<CallToolResult, McpError>| -> Value {
+        let rst = rst.unwrap();
+        assert_ne!(rst.is_error, Some(true));
+        serde_json::from_str(&rst.content[0].as_text().unwrap().text).unwrap()
+    };
+    let base = || Parameters(BaseRequest { chain_id: 1 });
+    let tx = || {
+        Parameters(TransactionRequest {
+            chain_id: 1,
+            transaction_hash: "0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060"
+                .parse()
+                .unwrap(),
+        })
+    };
+    let block = || {
+        Parameters(BlockRequest {
+            chain_id: 1,
+            number_or_hash: "19000000".parse().unwrap(),
+        })
+    };
+    let address = || {
+        Parameters(AddressRequest {
+            chain_id: 1,
+            address_hash: "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045"
+                .parse()
+                .unwrap(),
+        })
+    };
+    let token = || {
+        Parameters(TokenRequest {
+            chain_id: 1,
+            token_address: "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"
+                .parse()
+                .unwrap(),
+        })
+    };
+    let instance = || {
+        Parameters(TokenInstanceRequest {
+            chain_id: 1,
+            token_address: "0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D"
+                .parse()
+                .unwrap(),
+            token_id: 42,
+        })
+    };
+    let first = |rst| text(rst)["items"][0].clone();
+    let tx_graph = |format| {
+        Parameters(TransactionGraphRequest {
+            chain_id: 1,
+            transaction_hash: "0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060"
+                .parse()
+                .unwrap(),
+            format,
+        })
+    };
+    let address_graph = |format| {
+        Parameters(AddressGraphRequest {
+            chain_id: 1,
+            address_hash: "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045"
+                .parse()
+                .unwrap(),
+            format,
+        })
+    };
+    let diagram = |rst: Result<CallToolResult, McpError>| {
+        rst.unwrap().content[0].as_text().unwrap().text.clone()
+    };
+
+    let r = data
+        .search(Parameters(SearchRequest {
+            chain_id: 1,
+            q: "WETH".into(),
+        }))
+        .await;
+    assert_eq!(first(r)["symbol"], "WETH");
+    let r = data
+        .get_merlin_chain_info(Parameters(EmptyRequest {}))
+        .await;
+    assert_eq!(text(r)["native_token_symbol"], "BTC");
+
+    assert_eq!(first(data.get_transactions(base()).await)["nonce"], 1021);
+    assert_eq!(first(data.get_blocks(base()).await)["size"], 72531);
+    assert_eq!(first(data.get_transfers(base()).await)["log_index"], 112);
+    assert_eq!(
+        first(data.get_internal_transactions(base()).await)["gas_limit"],
+        "2300"
+    );
+    assert_eq!(first(data.get_withdrawals(base()).await)["index"], 30211456);
+    assert_eq!(
+        text(data.get_chain_stats(base()).await)["coin_price"],
+        "2503.12"
+    );
+
+    assert_eq!(
+        text(data.get_transaction_info(tx()).await)["gas_used"],
+        "45038"
+    );
+    assert_eq!(
+        first(data.get_transaction_token_transfers(tx()).await)["type"],
+        "token_minting"
+    );
+    assert_eq!(
+        first(
+            data.get_transaction_internal_transactions(tx_graph(GraphFormat::Json))
+                .await
+        )["success"],
+        true
+    );
+    assert_eq!(
+        diagram(
+            data.get_transaction_internal_transactions(tx_graph(GraphFormat::Mermaid))
+                .await
+        ),
+        "flowchart LR\n  n0[\"WETH9 (0xC02a…6Cc2)\"]\n  n1[\"vitalik.eth (0xd8dA…6045)\"]\n  n0 -->|\"#35;1 call\"| n1\n"
+    );
+    assert_eq!(first(data.get_transaction_logs(tx()).await)["index"], 112);
+    assert_eq!(
+        text(data.get_transaction_summary(tx()).await)["success"],
+        true
+    );
+
+    assert_eq!(
+        text(data.get_block_info(block()).await)["transaction_count"],
+        147
+    );
+    assert_eq!(
+        first(data.get_block_transactions(block()).await)["method"],
+        "deposit"
+    );
+    assert_eq!(
+        first(data.get_block_withdrawals(block()).await)["amount"],
+        "18012345000000000"
+    );
+
+    assert_eq!(first(data.get_addresses(base()).await)["is_contract"], true);
+    assert_eq!(
+        text(data.get_address_info(address()).await)["coin_balance"],
+        "1203456789012345678901"
+    );
+    assert_eq!(
+        text(data.get_address_counters(address()).await)["token_transfers_count"],
+        "4512"
+    );
+    assert_eq!(
+        first(data.get_address_transactions(address()).await)["status"],
+        "ok"
+    );
+    assert_eq!(
+        first(
+            data.get_address_token_transfers(address_graph(GraphFormat::Json))
+                .await
+        )["token"]["type"],
+        "ERC-20"
+    );
+    assert!(
+        diagram(
+            data.get_address_token_transfers(address_graph(GraphFormat::Dot))
+                .await
+        )
+        .contains("[label=\"1 WETH\"];")
+    );
+    assert_eq!(
+        first(data.get_address_internal_transactions(address()).await)["value"],
+        "0"
+    );
+    assert_eq!(first(data.get_address_logs(address()).await)["index"], 112);
+    let r = data
+        .get_address_logs(Parameters(AddressRequest {
+            chain_id: 1,
+            address_hash: "0x1111111111111111111111111111111111111111"
+                .parse()
+                .unwrap(),
+        }))
+        .await;
+    assert_eq!(first(r)["decoded"]["parameters"][2]["value"], "1000000000");
+    let r = data
+        .get_token_approvals(Parameters(AddressRequest {
+            chain_id: 1,
+            address_hash: "0x1111111111111111111111111111111111111111"
+                .parse()
+                .unwrap(),
+        }))
+        .await;
+    assert_eq!(
+        text(r)["approvals"][1]["spender_name"],
+        "Uniswap V2: Router 2"
+    );
+    let r = data
+        .get_address_portfolio(Parameters(AddressRequest {
+            chain_id: 1,
+            address_hash: "0x4444444444444444444444444444444444444444"
+                .parse()
+                .unwrap(),
+        }))
+        .await;
+    assert_eq!(text(r)["total_value_usd"], 9000.5);
+    assert_eq!(
+        first(data.get_address_tokens(address()).await)["value"],
+        "1000000000000000000"
+    );
+    assert_eq!(
+        first(data.get_address_coin_balance_history(address()).await)["block_number"],
+        19000000
+    );
+    assert_eq!(
+        text(
+            data.get_address_coin_balance_history_by_day(address())
+                .await
+        )["items"][0]["date"],
+        "2024-01-13"
+    );
+    assert_eq!(
+        first(data.get_address_withdrawals(address()).await)["validator_index"],
+        412003
+    );
+    assert_eq!(first(data.get_address_nfts(address()).await)["id"], "42");
+    assert_eq!(
+        first(data.get_address_nft_collections(address()).await)["token"]["type"],
+        "ERC-721"
+    );
+
+    assert_eq!(
+        first(data.get_tokens(base()).await)["name"],
+        "Wrapped Ether"
+    );
+    assert_eq!(
+        text(data.get_token_info(token()).await)["holders_count"],
+        "1093842"
+    );
+    assert_eq!(
+        first(data.get_token_transfers(token()).await)["block_number"],
+        19000000
+    );
+    assert_eq!(
+        first(data.get_token_holders(token()).await)["value"],
+        "1000000000000000000"
+    );
+    assert_eq!(
+        text(data.get_token_counters(token()).await)["transfers_count"],
+        "221903411"
+    );
+    assert_eq!(
+        first(data.get_token_instances(token()).await)["token"]["symbol"],
+        "BAYC"
+    );
+    assert_eq!(
+        text(data.get_token_instance_info(instance()).await)["metadata"]["name"],
+        "#42"
+    );
+    assert_eq!(
+        first(data.get_token_instance_transfers(instance()).await)["total"]["value"],
+        "1"
+    );
+    assert_eq!(
+        first(data.get_token_instance_holders(instance()).await)["token_id"],
+        "42"
+    );
+    assert_eq!(
+        text(data.get_token_instance_transfers_count(instance()).await)["transfers_count"],
+        7
+    );
+
+    let e = data
+        .get_chain_stats(Parameters(BaseRequest { chain_id: 999 }))
+        .await
+        .unwrap_err();
+    assert_eq!(e.code, ErrorCode::INTERNAL_ERROR);
+}
+
+#[tokio::test]
+async fn test_names() {
+    let mock = crate::mock_blockscout::MockBlockscout::start()
+        .await
+        .unwrap();
+    let data = OnChainData::with_api(mock.api());
+    let vitalik = "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045";
+    let by_name = |name: &str| {
+        Parameters(AddressRequest {
+            chain_id: 1,
+            address_hash: name.parse().unwrap(),
+        })
+    };
+
+    let rst = data
+        .get_address_counters(by_name("vitalik.eth"))
+        .await
+        .unwrap();
+    assert_eq!(rst.content.len(), 1);
+    let counters: Value = serde_json::from_str(&rst.content[0].as_text().unwrap().text).unwrap();
+    assert_eq!(
+        counters["resolved"],
+        json!({"name": "vitalik.eth", "address": vitalik})
+    );
+    assert!(
+        mock.requests()
+            .contains(&format!("1:addresses/{}/counters", vitalik))
+    );
+    let rst = data.get_address_counters(by_name(vitalik)).await.unwrap();
+    let counters: Value = serde_json::from_str(&rst.content[0].as_text().unwrap().text).unwrap();
+    assert!(counters.get("resolved").is_none());
+
+    let e = data
+        .get_address_info(by_name("nobody.eth"))
+        .await
+        .unwrap_err();
+    assert_eq!(e.code, ErrorCode::INVALID_PARAMS);
+    assert_eq!(
+        e.message,
+        "nobody.eth does not resolve to an address on chain 1"
+    );
+
+    // The fixture transaction comes without names, the sender has one.
+    let rst = data
+        .get_transaction_info(Parameters(TransactionRequest {
+            chain_id: 1,
+            transaction_hash: "0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060"
+                .parse()
+                .unwrap(),
+        }))
+        .await
+        .unwrap();
+    let tx: Value = serde_json::from_str(&rst.content[0].as_text().unwrap().text).unwrap();
+    assert_eq!(tx["from"]["ens_domain_name"], "vitalik.eth");
+    assert_eq!(tx["to"]["ens_domain_name"], Value::Null);
+
+    // Lists get the same treatment as single objects.
+    let rst = data
+        .get_address_transactions(by_name(vitalik))
+        .await
+        .unwrap();
+    let txs: Value = serde_json::from_str(&rst.content[0].as_text().unwrap().text).unwrap();
+    assert_eq!(txs["items"][0]["from"]["ens_domain_name"], "vitalik.eth");
+
+    let rst = data
+        .find_address_chains(Parameters(MultiChainAddressRequest {
+            address_hash: "vitalik.eth".parse().unwrap(),
+            chain_ids: Some(vec![1, 100, 999]),
+            include_testnets: None,
+        }))
+        .await
+        .unwrap();
+    assert_eq!(rst.content.len(), 1);
+    let activity: Value = serde_json::from_str(&rst.content[0].as_text().unwrap().text).unwrap();
+    assert_eq!(activity["address"], vitalik);
+    assert_eq!(activity["resolved"]["name"], "vitalik.eth");
+    assert_eq!(activity["active"][0]["chain_id"], 1);
+    assert_eq!(activity["inactive"], json!([100]));
+    assert!(activity["errors"]["999"].is_string());
+
+    // Too many chains to ask.
+    let e = data
+        .find_address_chains(Parameters(MultiChainAddressRequest {
+            address_hash: vitalik.parse().unwrap(),
+            chain_ids: Some((1..=multichain::MAX_CHAINS as i32 + 1).collect()),
+            include_testnets: None,
+        }))
+        .await
+        .unwrap_err();
+    assert_eq!(e.code, ErrorCode::INVALID_PARAMS);
+}
+
+#[tokio::test]
+async fn test_batch() {
+    let mock = crate::mock_blockscout::MockBlockscout::start()
+        .await
+        .unwrap();
+    let data = OnChainData::with_api(mock.api());
+    let known = "0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060";
+    let unknown = format!("0x{}", "0".repeat(64));
+    let text = |rst: CallToolResult| -> Value {
+        serde_json::from_str(&rst.content[0].as_text().unwrap().text).unwrap()
+    };
+
+    let rst = data
+        .get_transactions_info(Parameters(TransactionsRequest {
+            chain_id: 1,
+            transaction_hashes: vec![
+                known.parse().unwrap(),
+                unknown.parse().unwrap(),
+                known.to_uppercase().replace("0X", "0x").parse().unwrap(),
+            ],
+        }))
+        .await
+        .unwrap();
+    let rst = text(rst);
+    assert_eq!(rst["results"].as_object().unwrap().len(), 1);
+    assert_eq!(rst["results"][known]["hash"], known);
+    assert_eq!(rst["errors"][&unknown], "request failed: 404 Not Found");
+
+    let vitalik = "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045";
+    let rst = data
+        .get_addresses_info(Parameters(AddressesRequest {
+            chain_id: 1,
+            address_hashes: vec![
+                vitalik.parse().unwrap(),
+                "vitalik.eth".parse().unwrap(),
+                "nobody.eth".parse().unwrap(),
+            ],
+        }))
+        .await
+        .unwrap();
+    let rst = text(rst);
+    assert_eq!(rst["results"][vitalik]["hash"], vitalik);
+    assert_eq!(
+        rst["results"]["vitalik.eth"]["resolved"]["address"],
+        vitalik
+    );
+    assert_eq!(
+        rst["errors"]["nobody.eth"],
+        "nobody.eth does not resolve to an address on chain 1"
+    );
+
+    let too_many = (0..=MAX_BATCH_SIZE)
+        .map(|i| format!("0x{:064x}", i).parse().unwrap())
+        .collect();
+    let e = data
+        .get_transactions_info(Parameters(TransactionsRequest {
+            chain_id: 1,
+            transaction_hashes: too_many,
+        }))
+        .await
+        .unwrap_err();
+    assert_eq!(e.code, ErrorCode::INVALID_PARAMS);
+    assert_eq!(e.message, "the batch has 26 items, at most 25 are allowed");
 }
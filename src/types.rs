@@ -0,0 +1,302 @@
+use rmcp::schemars::{JsonSchema, Schema, SchemaGenerator};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+use tiny_keccak::{Hasher, Keccak};
+
+// Tool arguments end up in explorer url paths, so they are parsed into these types
+// before any request is made. Parse errors are plain messages meant for the caller,
+// they surface as INVALID_PARAMS through the serde `try_from`.
+
+fn strip_hex_prefix(value: &str) -> &str {
+    value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(value)
+}
+
+pub(crate) fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak::v256();
+    let mut output = [0u8; 32];
+    hasher.update(data);
+    hasher.finalize(&mut output);
+    output
+}
+
+// A 20 byte account or contract address, displayed in its EIP-55 checksum form.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Address(String);
+
+impl Address {
+    pub fn checksummed(hex: &str) -> String {
+        let hex = hex.to_ascii_lowercase();
+        let hash = keccak256(hex.as_bytes());
+        let checksummed: String = hex
+            .chars()
+            .enumerate()
+            .map(|(i, c)| {
+                let nibble = (hash[i / 2] >> if i % 2 == 0 { 4 } else { 0 }) & 0x0f;
+                if nibble >= 8 {
+                    c.to_ascii_uppercase()
+                } else {
+                    c
+                }
+            })
+            .collect();
+        format!("0x{}", checksummed)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for Address {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let hex = strip_hex_prefix(value);
+        if hex.len() != 40 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!(
+                "invalid address {:?}, expected 0x followed by 40 hex characters",
+                value
+            ));
+        }
+        let checksummed = Self::checksummed(hex);
+        // Mixed case means the caller used a checksum, a typo would break it.
+        let mixed_case = hex.chars().any(|c| c.is_ascii_lowercase())
+            && hex.chars().any(|c| c.is_ascii_uppercase());
+        if mixed_case && hex != &checksummed[2..] {
+            return Err(format!(
+                "invalid address checksum for {:?}, did you mean {}?",
+                value, checksummed
+            ));
+        }
+        Ok(Address(checksummed))
+    }
+}
+
+// A 32 byte transaction or block hash, displayed lowercase.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TxHash(String);
+
+impl TxHash {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for TxHash {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let hex = strip_hex_prefix(value);
+        if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            let hint = if hex.len() == 40 {
+                ", this looks like an address"
+            } else {
+                ""
+            };
+            return Err(format!(
+                "invalid hash {:?}, expected 0x followed by 64 hex characters{}",
+                value, hint
+            ));
+        }
+        Ok(TxHash(format!("0x{}", hex.to_ascii_lowercase())))
+    }
+}
+
+// A block number, decimal or 0x prefixed hex, or a block hash.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum BlockId {
+    Number(u64),
+    Hash(TxHash),
+}
+
+impl FromStr for BlockId {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let hex = strip_hex_prefix(value);
+        if hex.len() == 64 {
+            return value.parse().map(BlockId::Hash);
+        }
+        let number = if hex.len() < value.len() {
+            u64::from_str_radix(hex, 16).ok()
+        } else {
+            value.parse().ok()
+        };
+        number.map(BlockId::Number).ok_or_else(|| {
+            format!(
+                "invalid block {:?}, expected a block number or a 0x prefixed 32 byte block hash",
+                value
+            )
+        })
+    }
+}
+
+// An address, or a name like `vitalik.eth` still to be resolved to one.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum AddressOrName {
+    Address(Address),
+    Name(String),
+}
+
+impl FromStr for AddressOrName {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if value.starts_with("0x") || value.starts_with("0X") || !value.contains('.') {
+            return value.parse().map(AddressOrName::Address);
+        }
+        let name = value.to_lowercase();
+        let valid = name.len() <= 255
+            && name.split('.').all(|label| {
+                !label.is_empty()
+                    && label
+                        .chars()
+                        .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+            });
+        if !valid {
+            return Err(format!(
+                "invalid address or name {:?}, expected 0x followed by 40 hex characters or a name like vitalik.eth",
+                value
+            ));
+        }
+        Ok(AddressOrName::Name(name))
+    }
+}
+
+macro_rules! impl_string_conversions {
+    ($($ty:ty),*) => {
+        $(
+            impl TryFrom<String> for $ty {
+                type Error = String;
+
+                fn try_from(value: String) -> Result<Self, Self::Error> {
+                    value.parse()
+                }
+            }
+
+            impl From<$ty> for String {
+                fn from(value: $ty) -> Self {
+                    value.to_string()
+                }
+            }
+
+            // Same schema as a plain string, the validation happens on deserialization.
+            impl JsonSchema for $ty {
+                fn inline_schema() -> bool {
+                    true
+                }
+
+                fn schema_name() -> Cow<'static, str> {
+                    String::schema_name()
+                }
+
+                fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+                    String::json_schema(generator)
+                }
+            }
+        )*
+    };
+}
+
+impl_string_conversions!(Address, TxHash, BlockId, AddressOrName);
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl fmt::Display for TxHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl fmt::Display for AddressOrName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AddressOrName::Address(address) => address.fmt(f),
+            AddressOrName::Name(name) => f.write_str(name),
+        }
+    }
+}
+
+impl fmt::Display for BlockId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlockId::Number(number) => write!(f, "{}", number),
+            BlockId::Hash(hash) => hash.fmt(f),
+        }
+    }
+}
+
+#[test]
+fn test_parse() {
+    let vitalik = "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045";
+    let address: Address = vitalik.parse().unwrap();
+    assert_eq!(address.as_str(), vitalik);
+    let address: Address = " d8da6bf26964af9d7eed9e03e53415d37aa96045\n"
+        .parse()
+        .unwrap();
+    assert_eq!(address.as_str(), vitalik);
+    let e = "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96046"
+        .parse::<Address>()
+        .unwrap_err();
+    assert!(e.contains("did you mean 0xd8da6BF26964Af9d7EeD9E03e53415d37aa96046?"));
+    assert!(
+        "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA9604"
+            .parse::<Address>()
+            .is_err()
+    );
+    assert!("../stats".parse::<Address>().is_err());
+
+    let hash = "0x5C504ED432CB51138BCF09AA5E8A410DD4A1E204EF84BFED1BE16DFBA1B22060";
+    assert_eq!(
+        hash.parse::<TxHash>().unwrap().as_str(),
+        hash.to_ascii_lowercase()
+    );
+    assert!(
+        vitalik
+            .parse::<TxHash>()
+            .unwrap_err()
+            .contains("looks like an address")
+    );
+
+    assert_eq!("19000000".parse(), Ok(BlockId::Number(19000000)));
+    assert_eq!("0x121eac0".parse(), Ok(BlockId::Number(19000000)));
+    assert_eq!(
+        hash.parse::<BlockId>().unwrap().to_string(),
+        hash.to_ascii_lowercase()
+    );
+    assert!("latest".parse::<BlockId>().is_err());
+    assert!("-1".parse::<BlockId>().is_err());
+
+    assert_eq!(
+        "Vitalik.ETH ".parse(),
+        Ok(AddressOrName::Name("vitalik.eth".into()))
+    );
+    assert_eq!(
+        vitalik.parse::<AddressOrName>().unwrap().to_string(),
+        vitalik
+    );
+    assert!("vitalik".parse::<AddressOrName>().is_err());
+    assert!("vitalik..eth".parse::<AddressOrName>().is_err());
+    assert!("../vitalik.eth".parse::<AddressOrName>().is_err());
+    assert!("vitalik.eth/stats".parse::<AddressOrName>().is_err());
+
+    let e = serde_json::from_str::<Address>("\"0x12\"").unwrap_err();
+    assert!(e.to_string().starts_with("invalid address \"0x12\""));
+}
@@ -0,0 +1,190 @@
+use blocks_mcp::{mock_blockscout::MockBlockscout, tools::OnChainData};
+use rmcp::{
+    RoleClient, ServiceExt,
+    model::{CallToolRequestParam, ErrorCode, JsonObject, ProtocolVersion, Tool},
+    service::{RunningService, ServiceError},
+};
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, BTreeSet};
+
+const SCHEMAS_SNAPSHOT: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/snapshots/tool_schemas.json"
+);
+const UPDATE_SNAPSHOTS_ENV: &str = "UPDATE_SNAPSHOTS";
+
+// Runs the server and a client over an in-memory pipe, no http involved.
+async fn connect(mock: &MockBlockscout) -> RunningService<RoleClient, ()> {
+    let (server_io, client_io) = tokio::io::duplex(64 * 1024);
+    let server = OnChainData::with_api(mock.api());
+    tokio::spawn(async move {
+        let server = server.serve(server_io).await.unwrap();
+        server.waiting().await.unwrap();
+    });
+    ().serve(client_io).await.unwrap()
+}
+
+// Fills in every required argument with a value the fixtures know about.
+fn arguments(tool: &Tool) -> JsonObject {
+    let required = tool
+        .input_schema
+        .get("required")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    required
+        .iter()
+        .filter_map(Value::as_str)
+        .map(|name| {
+            let value = match name {
+                "chain_id" => json!(1),
+                "q" => json!("WETH"),
+                "transaction_hash" => {
+                    json!("0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060")
+                }
+                "transaction_hashes" => {
+                    json!(["0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060"])
+                }
+                "number_or_hash" => json!("19000000"),
+                "address_hash" => json!("0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045"),
+                "address_hashes" => json!(["0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045"]),
+                "token_address" if tool.name.starts_with("get_token_instance") => {
+                    json!("0xBC4CA0EdA7647A8aB7C2061c2E118A18a936f13D")
+                }
+                "token_address" => json!("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"),
+                "token_id" => json!(42),
+                name => panic!("no test value for argument {} of {}", name, tool.name),
+            };
+            (name.to_string(), value)
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_initialize() {
+    let mock = MockBlockscout::start().await.unwrap();
+    let client = connect(&mock).await;
+    let info = client.peer_info().unwrap();
+    assert_eq!(info.protocol_version, ProtocolVersion::V_2024_11_05);
+    assert!(info.capabilities.tools.is_some());
+    assert!(info.capabilities.prompts.is_some());
+    assert!(info.capabilities.completions.is_some());
+    assert_eq!(client.list_all_prompts().await.unwrap().len(), 3);
+    client.cancel().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_call_every_tool() {
+    let mock = MockBlockscout::start().await.unwrap();
+    let client = connect(&mock).await;
+    let tools = client.list_all_tools().await.unwrap();
+    assert_eq!(tools.len(), 46);
+    for tool in &tools {
+        let result = client
+            .call_tool(CallToolRequestParam {
+                name: tool.name.clone(),
+                arguments: Some(arguments(tool)),
+            })
+            .await
+            .unwrap_or_else(|e| panic!("{} failed: {}", tool.name, e));
+        assert_ne!(
+            result.is_error,
+            Some(true),
+            "{} returned an error",
+            tool.name
+        );
+        let text = &result.content[0].as_text().unwrap().text;
+        let data: Value = serde_json::from_str(text).unwrap();
+        assert!(data.is_object(), "{} returned {}", tool.name, data);
+    }
+    client.cancel().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_invalid_calls() {
+    let mock = MockBlockscout::start().await.unwrap();
+    let client = connect(&mock).await;
+    let call = |name: &'static str, arguments: Value| {
+        client.call_tool(CallToolRequestParam {
+            name: name.into(),
+            arguments: arguments.as_object().cloned(),
+        })
+    };
+    let code = |e: ServiceError| match e {
+        ServiceError::McpError(e) => e.code,
+        e => panic!("unexpected error {}", e),
+    };
+
+    let e = call("no_such_tool", json!({})).await.unwrap_err();
+    assert_eq!(code(e), ErrorCode::INVALID_PARAMS);
+    let e = call("get_chain_stats", json!({})).await.unwrap_err();
+    assert_eq!(code(e), ErrorCode::INVALID_PARAMS);
+    let e = call("get_chain_stats", json!({"chain_id": "one"}))
+        .await
+        .unwrap_err();
+    assert_eq!(code(e), ErrorCode::INVALID_PARAMS);
+    let e = call(
+        "get_address_info",
+        json!({"chain_id": 1, "address_hash": "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA9604"}),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(code(e), ErrorCode::INVALID_PARAMS);
+    let e = call(
+        "get_block_info",
+        json!({"chain_id": 1, "number_or_hash": "../stats"}),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(code(e), ErrorCode::INVALID_PARAMS);
+    assert!(mock.requests().is_empty());
+    let e = call("get_chain_stats", json!({"chain_id": 999}))
+        .await
+        .unwrap_err();
+    assert_eq!(code(e), ErrorCode::INTERNAL_ERROR);
+    let metrics = blocks_mcp::metrics::METRICS.encode();
+    assert!(!metrics.contains("no_such_tool"));
+    assert!(metrics.contains("tool=\"unknown\""));
+    client.cancel().await.unwrap();
+}
+
+// Tool schemas are generated from the `*Request` structs, a change to them is a
+// change to the public interface. Run with UPDATE_SNAPSHOTS=1 to accept it.
+#[tokio::test]
+async fn test_tool_schemas_snapshot() {
+    let mock = MockBlockscout::start().await.unwrap();
+    let client = connect(&mock).await;
+    let schemas: BTreeMap<String, Value> = client
+        .list_all_tools()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|tool| {
+            let schema = json!({
+                "description": tool.description,
+                "input_schema": *tool.input_schema,
+            });
+            (tool.name.to_string(), schema)
+        })
+        .collect();
+    client.cancel().await.unwrap();
+
+    let actual = serde_json::to_string_pretty(&schemas).unwrap() + "\n";
+    if std::env::var(UPDATE_SNAPSHOTS_ENV).is_ok() {
+        std::fs::write(SCHEMAS_SNAPSHOT, &actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(SCHEMAS_SNAPSHOT).unwrap_or_default();
+    let expected: BTreeMap<String, Value> = serde_json::from_str(&expected).unwrap_or_default();
+    let changed: BTreeSet<&String> = schemas
+        .keys()
+        .chain(expected.keys())
+        .filter(|name| schemas.get(*name) != expected.get(*name))
+        .collect();
+    assert!(
+        changed.is_empty(),
+        "tool schemas changed for {:?}, rerun with {}=1 if this is intended",
+        changed,
+        UPDATE_SNAPSHOTS_ENV
+    );
+}